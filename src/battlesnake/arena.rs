@@ -0,0 +1,387 @@
+//! Local game simulator for self-play
+//!
+//! Plays standard rules games between contestants, each driven by the same
+//! strategies the server uses but with its own settings. Moves are resolved by
+//! `Board::advance_snakes`, the arena only adds starting positions and food spawning.
+//! Games can be recorded as replays, one per contestant, to serve as training data.
+//!
+use log::*;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::config::Settings;
+use super::game::GameContext;
+use super::input::{ApiSnake, Board as ApiBoard, GameData, Ruleset};
+use super::replay::{Recorder, ReplayRecord};
+use super::strategy::{validate_move, Strategy};
+use super::Board;
+use super::Direction;
+use super::GameInfo;
+use super::Point;
+
+/// Contestants a game has starting positions for
+pub const MAX_SNAKES: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct ArenaParams {
+    pub width: i32,
+    pub height: i32,
+    /// games still running after this many turns are a draw
+    pub max_turns: i32,
+    /// search time per move
+    pub time_budget: Duration,
+    /// chance of spawning food each turn
+    pub food_spawn_chance: f32,
+    pub min_food: usize,
+}
+
+impl Default for ArenaParams {
+    fn default() -> Self {
+        Self {
+            width: 11,
+            height: 11,
+            max_turns: 500,
+            time_budget: Duration::from_millis(20),
+            food_spawn_chance: 0.15,
+            min_food: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Contestant {
+    pub name: String,
+    pub settings: Settings,
+}
+
+impl Contestant {
+    pub fn new(name: &str, settings: Settings) -> Self {
+        Self {
+            name: name.to_string(),
+            settings,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameResult {
+    /// index of the winning contestant, none for a draw
+    pub winner: Option<usize>,
+    pub turns: i32,
+}
+
+/// Plays a single game, snake ids are the contestants' indices
+pub fn play_game(contestants: &[Contestant], params: &ArenaParams, seed: u64) -> GameResult {
+    play_game_recorded(contestants, params, seed, None)
+}
+
+/// Plays a single game and records it from every contestant's view,
+/// game ids of the views get the contestant's index appended
+pub fn play_game_recorded(
+    contestants: &[Contestant],
+    params: &ArenaParams,
+    seed: u64,
+    recorder: Option<&Recorder>,
+) -> GameResult {
+    let record = |mut state: GameInfo, i: usize, chosen: Option<(Direction, [Option<f32>; 4])>| {
+        if let Some(recorder) = recorder {
+            state.game.id = format!("{}-{}", state.game.id, i);
+            let record = match chosen {
+                Some((chosen, scores)) => ReplayRecord::Move {
+                    state,
+                    chosen,
+                    scores,
+                },
+                None => ReplayRecord::End { state },
+            };
+            if let Err(e) = recorder.record(&record) {
+                warn!("{}", e);
+            }
+        }
+    };
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut game = new_game(contestants, params, &mut rng, seed);
+    let evaluators: Vec<_> = contestants
+        .iter()
+        .map(|c| c.settings.evaluator.build())
+        .collect();
+    let strategies: Vec<Box<dyn Strategy>> = contestants
+        .iter()
+        .map(|c| c.settings.strategy.build())
+        .collect();
+    let mut contexts: Vec<GameContext> = game
+        .board
+        .snakes
+        .iter()
+        .map(|snake| {
+            let view = get_view(&game, snake);
+            let mut ctx = GameContext::new(&view, Arc::default());
+            strategies[get_contestant(snake)].start(&view, &mut ctx);
+            ctx
+        })
+        .collect();
+    let mut last_seen = game.board.snakes.clone();
+
+    let min_alive = if contestants.len() > 1 { 2 } else { 1 };
+    while game.board.snakes.len() >= min_alive && game.turn < params.max_turns {
+        let moves: Vec<Direction> = game
+            .board
+            .snakes
+            .iter()
+            .map(|snake| {
+                let i = get_contestant(snake);
+                let view = get_view(&game, snake);
+                contexts[i].observe(&view);
+                let settings = &contestants[i].settings;
+                let movement = strategies[i].get_move(
+                    &view,
+                    &mut contexts[i],
+                    params.time_budget,
+                    settings,
+                    &evaluators[i],
+                );
+                let movement = validate_move(&view, &mut contexts[i], settings, movement).movement;
+                let scores = contexts[i]
+                    .search
+                    .as_ref()
+                    .map(|s| s.root_scores)
+                    .unwrap_or_default();
+                record(view, i, Some((movement, scores)));
+                movement
+            })
+            .collect();
+        for snake in game.board.snakes.iter() {
+            last_seen[get_contestant(snake)] = snake.clone();
+        }
+        advance(&mut game, &moves);
+        spawn_food(&mut game, params, &mut rng);
+    }
+
+    for (i, snake) in last_seen.iter().enumerate() {
+        let mut view = get_view(&game, snake);
+        // survivors end with their final body
+        if let Some(alive) = game.board.snakes.iter().find(|s| s.id == snake.id) {
+            view.you = alive.clone();
+        }
+        strategies[i].end(&view, &mut contexts[i]);
+        record(view, i, None);
+    }
+
+    let winner = match game.board.snakes.as_slice() {
+        [snake] if contestants.len() > 1 => Some(get_contestant(snake)),
+        _ => None,
+    };
+    GameResult {
+        winner,
+        turns: game.turn,
+    }
+}
+
+fn get_contestant(snake: &ApiSnake) -> usize {
+    snake.id.parse().unwrap()
+}
+
+/// Game as seen by `snake`
+fn get_view(game: &GameInfo, snake: &ApiSnake) -> GameInfo {
+    let mut view = game.clone();
+    view.you = snake.clone();
+    view
+}
+
+/// Snakes start stacked on standard starting positions, with food in the center
+fn new_game(
+    contestants: &[Contestant],
+    params: &ArenaParams,
+    rng: &mut StdRng,
+    seed: u64,
+) -> GameInfo {
+    let (w, h) = (params.width, params.height);
+    let mut starts: [Point; MAX_SNAKES] = [
+        Point::new(1, 1),
+        Point::new(w - 2, h - 2),
+        Point::new(1, h - 2),
+        Point::new(w - 2, 1),
+        Point::new(w / 2, 1),
+        Point::new(w / 2, h - 2),
+        Point::new(1, h / 2),
+        Point::new(w - 2, h / 2),
+    ];
+    assert!(contestants.len() <= starts.len(), "Too many contestants");
+    starts.shuffle(rng);
+    let snakes: Vec<ApiSnake> = contestants
+        .iter()
+        .zip(starts.iter())
+        .enumerate()
+        .map(|(i, (contestant, start))| ApiSnake {
+            id: i.to_string(),
+            name: contestant.name.clone(),
+            health: 100,
+            body: vec![*start; 3],
+            head: *start,
+            length: 3,
+            shout: String::new(),
+        })
+        .collect();
+    let mut game = GameInfo {
+        game: GameData {
+            id: format!("arena-{}", seed),
+            ruleset: Ruleset {
+                name: "standard".to_string(),
+                version: String::new(),
+            },
+            timeout: 500,
+        },
+        turn: 0,
+        board: ApiBoard {
+            height: h,
+            width: w,
+            food: vec![Point::new(w / 2, h / 2)],
+            hazards: Vec::new(),
+            snakes,
+        },
+        you: ApiSnake {
+            id: String::new(),
+            name: String::new(),
+            health: 0,
+            body: Vec::new(),
+            head: Point::zero(),
+            length: 0,
+            shout: String::new(),
+        },
+    };
+    for _ in 0..contestants.len() {
+        add_food(&mut game, rng);
+    }
+    game
+}
+
+/// Moves all snakes, `moves` are in the order of `game.board.snakes`
+fn advance(game: &mut GameInfo, moves: &[Direction]) {
+    if game.board.snakes.is_empty() {
+        return;
+    }
+    // first snake as `you` keeps the board in the api order
+    let mut board = Board::from_api(&get_view(game, &game.board.snakes[0]));
+    board.advance_snakes(moves, &game.board.hazards);
+    game.board.snakes = board
+        .snakes
+        .iter()
+        .map(|snake| {
+            let mut api_snake = game.board.snakes[snake.id].clone();
+            api_snake.body = snake.body.nodes.clone();
+            api_snake.head = snake.head();
+            api_snake.length = snake.size() as i32;
+            api_snake.health = snake.health as i32;
+            api_snake
+        })
+        .collect();
+    game.board.food = board.food;
+    game.turn += 1;
+}
+
+fn spawn_food(game: &mut GameInfo, params: &ArenaParams, rng: &mut StdRng) {
+    if game.board.food.len() < params.min_food || rng.gen::<f32>() < params.food_spawn_chance {
+        add_food(game, rng);
+    }
+}
+
+/// Adds food on a random free cell
+fn add_food(game: &mut GameInfo, rng: &mut StdRng) {
+    let occupied: Vec<Point> = game
+        .board
+        .snakes
+        .iter()
+        .flat_map(|s| s.body.iter().cloned())
+        .chain(game.board.food.iter().cloned())
+        .collect();
+    let free: Vec<Point> = (0..game.board.width)
+        .flat_map(|x| (0..game.board.height).map(move |y| Point::new(x, y)))
+        .filter(|p| !occupied.contains(p))
+        .collect();
+    if let Some(p) = free.choose(rng) {
+        game.board.food.push(*p);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::replay::Replay;
+    use super::*;
+    use std::collections::HashSet;
+    use std::fs;
+
+    fn contestants(n: usize) -> Vec<Contestant> {
+        (0..n)
+            .map(|i| Contestant::new(&format!("snake {}", i), Settings::default()))
+            .collect()
+    }
+
+    #[test]
+    fn rules() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let game = new_game(&contestants(4), &ArenaParams::default(), &mut rng, 7);
+        assert_eq!(game.board.snakes.len(), 4);
+        assert_eq!(game.board.food.len(), 5);
+        let heads: HashSet<Point> = game.get_heads().into_iter().collect();
+        assert_eq!(heads.len(), 4);
+
+        // first snake eats, second runs into the wall
+        let mut game = new_game(&contestants(2), &ArenaParams::default(), &mut rng, 7);
+        game.board.snakes[0].body = vec![Point::new(1, 1); 3];
+        game.board.snakes[0].head = Point::new(1, 1);
+        game.board.snakes[1].body = vec![Point::new(0, 5); 3];
+        game.board.snakes[1].head = Point::new(0, 5);
+        game.board.food = vec![Point::new(2, 1), Point::new(5, 5)];
+        advance(&mut game, &[Direction::Right, Direction::Left]);
+        assert_eq!(game.turn, 1);
+        assert_eq!(game.board.snakes.len(), 1);
+        let snake = &game.board.snakes[0];
+        assert_eq!(snake.id, "0");
+        assert_eq!(snake.head, Point::new(2, 1));
+        assert_eq!(snake.length, 4);
+        assert_eq!(snake.health, 100);
+        assert_eq!(game.board.food, vec![Point::new(5, 5)]);
+    }
+
+    #[test]
+    fn play() {
+        let params = ArenaParams {
+            max_turns: 30,
+            time_budget: Duration::from_millis(5),
+            ..ArenaParams::default()
+        };
+        let result = play_game(&contestants(2), &params, 1);
+        assert!(result.turns > 0 && result.turns <= 30);
+        assert!(matches!(result.winner, None | Some(0) | Some(1)));
+    }
+
+    #[test]
+    fn record() {
+        let dir = std::env::temp_dir().join(format!("battlesnake-arena-{}", std::process::id()));
+        let recorder = Recorder::new(dir.to_str().unwrap()).unwrap();
+        let params = ArenaParams {
+            width: 7,
+            height: 7,
+            max_turns: 20,
+            time_budget: Duration::from_millis(2),
+            ..ArenaParams::default()
+        };
+        let result = play_game_recorded(&contestants(2), &params, 3, Some(&recorder));
+        let replays = Replay::load_dir(dir.to_str().unwrap()).unwrap();
+        assert_eq!(replays.len(), 2);
+        for (i, replay) in replays.iter().enumerate() {
+            let moves = replay.get_moves();
+            assert!(!moves.is_empty() && moves.len() as i32 <= result.turns);
+            assert!(moves.iter().all(|(state, _)| state.you.id == i.to_string()));
+            match result.winner {
+                Some(w) if w == i => assert_eq!(replay.get_outcome(), Some(1.0)),
+                Some(_) => assert_eq!(replay.get_outcome(), Some(0.0)),
+                None => assert!(matches!(replay.get_outcome(), Some(o) if o < 1.0)),
+            }
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,7 +1,7 @@
-use std::cmp::{Eq, Ord, Ordering, PartialEq, PartialOrd};
-use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 
+use super::pathfinding::{self, BoardCost};
 use super::GameInfo;
 use super::Path;
 use super::Point;
@@ -24,30 +24,8 @@ pub enum CauseOfDeath {
     OutOfHealth,
 }
 
-/// A* star queue nodes (point to explore, f_score)
-#[derive(Eq)]
-struct OpenNode(Point, usize);
-
-impl Ord for OpenNode {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.1.cmp(&other.1).reverse() //we want a min heap
-    }
-}
-
-impl PartialOrd for OpenNode {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-impl PartialEq for OpenNode {
-    fn eq(&self, other: &Self) -> bool {
-        self.1 == other.1
-    }
-}
-
-// Static heuristics weight, assuming all tiles are hazard
-const H_WEIGHT: usize = 3 + 15;
+/// Default cost of stepping on a hazard tile
+pub const HAZARD_COST: usize = 16;
 
 impl Board {
     pub fn from_api(input: &GameInfo) -> Self {
@@ -55,14 +33,16 @@ impl Board {
         snakes.push(Snake::try_from(&input.you).unwrap());
         for snake in input.board.snakes.iter() {
             if snake.id != input.you.id {
-                snakes.push(Snake::try_from(snake).unwrap());
+                let mut new_snake = Snake::try_from(snake).unwrap();
+                new_snake.id = snakes.len();
+                snakes.push(new_snake);
             }
         }
         let dim = input.get_board_dimensions();
         Self {
             snakes,
             food: input.get_food(),
-            bound: Point::new(dim.0 as i32 - 1, dim.1 as i32 - 1),
+            bound: Point::new(dim.1 as i32 - 1, dim.0 as i32 - 1),
         }
     }
 
@@ -70,22 +50,24 @@ impl Board {
         ALL_DIRECTIONS
             .iter()
             .cloned()
-            .filter(|dir| {
-                let new = *p + *dir;
-                self.is_inbounds(&new)
-                    && self.snakes.iter().all(|snake| {
-                        if new.manhattan_distance(&snake.head()) as usize > snake.size() {
-                            true
-                        } else if let Some(i) = snake.is_collision(&new) {
-                            snake.size() >= n && i >= snake.size() - n
-                        } else {
-                            true
-                        }
-                    })
-            })
+            .filter(|dir| self.is_free(&(*p + *dir), n))
             .collect()
     }
 
+    /// Whether `p` is inbounds and not covered by any snake `n` turns from now
+    pub fn is_free(&self, p: &Point, n: usize) -> bool {
+        self.is_inbounds(p)
+            && self.snakes.iter().all(|snake| {
+                if p.manhattan_distance(&snake.head()) > snake.size() {
+                    true
+                } else if let Some(i) = snake.is_collision(p) {
+                    snake.size() >= n && i >= snake.size() - n
+                } else {
+                    true
+                }
+            })
+    }
+
     pub fn get_all_moves(&self) -> Vec<Vec<Direction>> {
         let mut ret: Vec<Vec<Direction>> = Vec::new();
         for snake in self.snakes.iter() {
@@ -192,58 +174,50 @@ impl Board {
         self.food.iter().position(|food| food == pos)
     }
 
-    fn is_inbounds(&self, pos: &Point) -> bool {
+    pub fn is_inbounds(&self, pos: &Point) -> bool {
         pos.is_not_negative() && (pos.x <= self.bound.x && pos.y <= self.bound.y)
     }
 
-    pub fn astar(&self, s: Point, e: Point, hazards: &Vec<Point>) -> Option<(usize, Path)> {
-        // keeps open points and the f_cost
-        let mut openset: BinaryHeap<OpenNode> = BinaryHeap::new();
-        // keeps min cost per point <point, (g_cost, turns, parent)
-        let mut closedset: HashMap<Point, (usize, usize, Option<Point>)> = HashMap::new();
+    pub fn get_width(&self) -> usize {
+        (self.bound.x + 1) as usize
+    }
 
-        openset.push(OpenNode(s, s.manhattan_distance(&e) * H_WEIGHT));
-        closedset.insert(s, (0, 0, None));
+    pub fn get_height(&self) -> usize {
+        (self.bound.y + 1) as usize
+    }
 
-        while let Some(OpenNode(point, _)) = openset.pop() {
-            let (mut g_score, turn) = closedset
-                .get(&point)
-                .map(|(g, t, ..)| (*g, *t + 1))
-                .unwrap_or((0, 0));
-            if point == e {
-                let mut nodes = vec![point];
-                while let Some((_, _, Some(parent))) = closedset.get(nodes.last().unwrap()) {
-                    nodes.push(*parent);
-                }
-                if hazards.contains(&point) {
-                    g_score -= 15;
-                }
-                return Some((g_score, Path::from_vec(nodes)));
-            }
+    /// Index of an inbound point in row-major grid vectors
+    pub fn get_index(&self, pos: &Point) -> usize {
+        pos.y as usize * self.get_width() + pos.x as usize
+    }
 
-            let to_explore = self
-                .get_pruned_moves(&point, turn)
-                .iter()
-                .map(|x| point + x)
-                .collect::<Vec<_>>();
+    pub fn get_point(&self, index: usize) -> Point {
+        Point::new(
+            (index % self.get_width()) as i32,
+            (index / self.get_width()) as i32,
+        )
+    }
 
-            for n in to_explore {
-                let new_g_score = if hazards.contains(&n) {
-                    g_score + 16
-                } else {
-                    g_score + 1
-                };
-                let old_g_score = closedset.get(&n);
-                if old_g_score.is_none() || new_g_score < (*old_g_score.unwrap()).0 {
-                    closedset.insert(n, (new_g_score, turn, Some(point)));
-                    openset.push(OpenNode(
-                        n,
-                        new_g_score + n.manhattan_distance(&e) * H_WEIGHT,
-                    ))
-                }
-            }
+    pub fn astar(&self, s: Point, e: Point, hazards: &Vec<Point>) -> Option<(usize, Path)> {
+        self.astar_with_cost(s, e, hazards, HAZARD_COST)
+    }
+
+    /// A* where stepping on a hazard costs `hazard_cost` instead of 1.
+    /// Bodies are passable once vacated, a path starting on a snake's head grows it by the food eaten.
+    pub fn astar_with_cost(
+        &self,
+        s: Point,
+        e: Point,
+        hazards: &[Point],
+        hazard_cost: usize,
+    ) -> Option<(usize, Path)> {
+        let mover = self.snakes.iter().position(|snake| snake.head() == s);
+        let cost = BoardCost::new(self, mover, hazards, hazard_cost);
+        let (mut g_score, path) = pathfinding::astar(&cost, s, e)?;
+        if hazards.contains(&e) {
+            g_score = g_score.saturating_sub(hazard_cost - 1);
         }
-        None
+        Some((g_score, path))
     }
 }
 
@@ -481,6 +455,34 @@ mod test {
         );
     }
 
+    #[test]
+    fn non_square_bounds() {
+        // 7 wide, 4 high
+        let board = Board::from_api(&GameInfo::new(
+            r#"{
+            "game": {"id": "game-1", "timeout": 500},
+            "turn": 5,
+            "board": {
+                "height": 4,
+                "width": 7,
+                "food": [],
+                "hazards": [],
+                "snakes": [
+                    {"id": "me", "name": "me", "health": 90, "body": [{"x": 5, "y": 0}, {"x": 4, "y": 0}, {"x": 3, "y": 0}], "head": {"x": 5, "y": 0}, "length": 3, "shout": ""}
+                ]
+            },
+            "you": {"id": "me", "name": "me", "health": 90, "body": [{"x": 5, "y": 0}, {"x": 4, "y": 0}, {"x": 3, "y": 0}], "head": {"x": 5, "y": 0}, "length": 3, "shout": ""}
+        }"#,
+        ));
+        assert!(board.is_inbounds(&Point::new(6, 3)));
+        assert!(!board.is_inbounds(&Point::new(7, 0)));
+        assert!(!board.is_inbounds(&Point::new(0, 4)));
+        assert_eq!(
+            board.get_pruned_moves(&Point::new(5, 0), 1),
+            vec![Direction::Right, Direction::Up]
+        );
+    }
+
     #[test]
     fn available_moves() {
         let gameinfo = GameInfo::new(
@@ -1287,14 +1289,19 @@ mod test {
         let path = board.astar(Point { x: 9, y: 3 }, Point { x: 10, y: 6 }, hazards);
         assert!(path.is_some());
         let (g_score, path) = path.unwrap();
-        assert_eq!(g_score, 49);
+        // following our own tail around is cheaper than the hazard lane
+        assert_eq!(g_score, 8);
         assert_eq!(
             path.nodes,
             vec![
                 Point { x: 10, y: 6 },
-                Point { x: 10, y: 5 },
-                Point { x: 10, y: 4 },
-                Point { x: 10, y: 3 },
+                Point { x: 9, y: 6 },
+                Point { x: 9, y: 5 },
+                Point { x: 8, y: 5 },
+                Point { x: 7, y: 5 },
+                Point { x: 7, y: 4 },
+                Point { x: 8, y: 4 },
+                Point { x: 8, y: 3 },
                 Point { x: 9, y: 3 },
             ]
         );
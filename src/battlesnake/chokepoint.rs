@@ -0,0 +1,236 @@
+//! Chokepoints in the free space around the snakes
+//!
+//! Free cells, those empty once the tails moved on, form a graph that a snake
+//! enters through its head. A depth first search from the head finds the
+//! articulation points of that graph, the cells without which part of the
+//! board is cut off. An articulation point that leaves less room on the side
+//! of the head than behind it is the exit of a pocket; a narrow corridor shows
+//! up as a run of such exits, one per cell.
+//!
+//! Whoever gets to an exit first can close it with its body. Snakes arriving on
+//! the same turn meet head to head, so there the longer one closes it.
+//!
+use super::distance::DistanceMaps;
+use super::Board;
+use super::Point;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pocket {
+    /// cell connecting the pocket to the rest of the board
+    pub exit: Point,
+    /// free cells on the side of the head, the exit excluded
+    pub inside: usize,
+    /// free cells cut off by closing the exit
+    pub outside: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Chokepoints {
+    /// per snake, exits of the pockets it is in, innermost first
+    pockets: Vec<Vec<Pocket>>,
+    sizes: Vec<usize>,
+}
+
+/// Articulation point search rooted at a head
+struct Search<'a> {
+    board: &'a Board,
+    free: &'a [bool],
+    /// the head, entered from the free cells around it
+    root: usize,
+    /// discovery time of each cell
+    discovered: Vec<Option<usize>>,
+    /// lowest discovery time reachable from the subtree of each cell
+    low: Vec<usize>,
+    /// cells in the subtree of each cell
+    subtree: Vec<usize>,
+    /// cells cut off from the root by each articulation point
+    cut: Vec<usize>,
+    time: usize,
+}
+
+impl<'a> Search<'a> {
+    fn visit(&mut self, cell: usize, parent: Option<usize>) {
+        self.discovered[cell] = Some(self.time);
+        self.low[cell] = self.time;
+        self.subtree[cell] = 1;
+        self.time += 1;
+        for n in self.board.get_point(cell).get_neighbours().iter() {
+            if !self.board.is_inbounds(n) {
+                continue;
+            }
+            let next = self.board.get_index(n);
+            if !self.free[next] && next != self.root {
+                continue;
+            }
+            match self.discovered[next] {
+                None => {
+                    self.visit(next, Some(cell));
+                    self.subtree[cell] += self.subtree[next];
+                    self.low[cell] = self.low[cell].min(self.low[next]);
+                    if parent.is_some() && self.low[next] >= self.discovered[cell].unwrap() {
+                        self.cut[cell] += self.subtree[next];
+                    }
+                }
+                Some(time) if Some(next) != parent => {
+                    self.low[cell] = self.low[cell].min(time);
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+impl Chokepoints {
+    pub fn new(board: &Board) -> Self {
+        let times = board.get_vacate_times();
+        let free: Vec<bool> = times.iter().map(|t| *t <= 1).collect();
+        let pockets = board
+            .snakes
+            .iter()
+            .map(|snake| {
+                let head = snake.head();
+                if !board.is_inbounds(&head) {
+                    return Vec::new();
+                }
+                let root = board.get_index(&head);
+                let mut search = Search {
+                    board,
+                    free: &free,
+                    root,
+                    discovered: vec![None; times.len()],
+                    low: vec![0; times.len()],
+                    subtree: vec![0; times.len()],
+                    cut: vec![0; times.len()],
+                    time: 0,
+                };
+                search.visit(root, None);
+                let total = search.subtree[root];
+                let mut pockets: Vec<Pocket> = (0..times.len())
+                    .filter(|cell| *cell != root && search.cut[*cell] > 0)
+                    .map(|cell| Pocket {
+                        exit: board.get_point(cell),
+                        inside: total - search.cut[cell] - 2,
+                        outside: search.cut[cell],
+                    })
+                    .filter(|pocket| pocket.inside < pocket.outside)
+                    .collect();
+                pockets.sort_by_key(|pocket| pocket.inside);
+                pockets
+            })
+            .collect();
+        Self {
+            pockets,
+            sizes: board.snakes.iter().map(|s| s.size()).collect(),
+        }
+    }
+
+    /// Exits of the pockets snake `index` is in, innermost first
+    pub fn get_pockets(&self, index: usize) -> &[Pocket] {
+        &self.pockets[index]
+    }
+
+    /// Whether snake `closer` gets to `pocket` of snake `owner` in time to close it
+    pub fn can_close(
+        &self,
+        distances: &DistanceMaps,
+        closer: usize,
+        owner: usize,
+        pocket: &Pocket,
+    ) -> bool {
+        match (
+            distances.get_steps(closer, &pocket.exit),
+            distances.get_steps(owner, &pocket.exit),
+        ) {
+            (Some(theirs), Some(mine)) => {
+                theirs < mine || (theirs == mine && self.sizes[closer] > self.sizes[owner])
+            }
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
+    /// Smallest pocket of snake `index` another snake can close
+    pub fn get_threat(&self, distances: &DistanceMaps, index: usize) -> Option<&Pocket> {
+        self.get_pockets(index).iter().find(|pocket| {
+            (0..self.sizes.len())
+                .filter(|i| *i != index)
+                .any(|i| self.can_close(distances, i, index, pocket))
+        })
+    }
+
+    /// Other snakes whose pocket snake `index` can close
+    pub fn get_cut_off(&self, distances: &DistanceMaps, index: usize) -> Vec<usize> {
+        (0..self.sizes.len())
+            .filter(|i| *i != index)
+            .filter(|i| {
+                self.get_pockets(*i)
+                    .iter()
+                    .any(|pocket| self.can_close(distances, index, *i, pocket))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::GameInfo;
+    use super::*;
+
+    #[test]
+    fn pockets() {
+        // we are in the bottom left, behind a corridor a longer snake is next to
+        let board = Board::from_api(&GameInfo::new(
+            r#"{
+            "game": {"id": "game-1", "timeout": 500},
+            "turn": 5,
+            "board": {
+                "height": 7,
+                "width": 7,
+                "food": [],
+                "hazards": [],
+                "snakes": [
+                    {"id": "me", "name": "me", "health": 90, "body": [
+                        {"x": 1, "y": 1}, {"x": 0, "y": 1}, {"x": 0, "y": 0}, {"x": 1, "y": 0}, {"x": 2, "y": 0}
+                    ], "head": {"x": 1, "y": 1}, "length": 5, "shout": ""},
+                    {"id": "other", "name": "other", "health": 90, "body": [
+                        {"x": 2, "y": 4}, {"x": 3, "y": 4}, {"x": 3, "y": 3}, {"x": 3, "y": 2}, {"x": 3, "y": 1}, {"x": 3, "y": 0}, {"x": 4, "y": 0}
+                    ], "head": {"x": 2, "y": 4}, "length": 7, "shout": ""},
+                    {"id": "third", "name": "third", "health": 90, "body": [
+                        {"x": 1, "y": 2}, {"x": 0, "y": 2}, {"x": 0, "y": 3}
+                    ], "head": {"x": 1, "y": 2}, "length": 3, "shout": ""}
+                ]
+            },
+            "you": {"id": "me", "name": "me", "health": 90, "body": [
+                {"x": 1, "y": 1}, {"x": 0, "y": 1}, {"x": 0, "y": 0}, {"x": 1, "y": 0}, {"x": 2, "y": 0}
+            ], "head": {"x": 1, "y": 1}, "length": 5, "shout": ""}
+        }"#,
+        ));
+        let chokepoints = Chokepoints::new(&board);
+        let exits: Vec<(Point, usize)> = chokepoints
+            .get_pockets(0)
+            .iter()
+            .map(|pocket| (pocket.exit, pocket.inside))
+            .collect();
+        assert_eq!(
+            exits,
+            vec![
+                (Point::new(2, 1), 0),
+                (Point::new(2, 2), 2),
+                (Point::new(2, 3), 3),
+                (Point::new(1, 3), 4)
+            ]
+        );
+
+        let distances = board.get_distance_maps(&[], 1);
+        // we get to the first cell of the corridor first, not to the others
+        let threat = chokepoints.get_threat(&distances, 0).unwrap();
+        assert_eq!(threat.exit, Point::new(2, 2));
+        assert_eq!(chokepoints.get_cut_off(&distances, 1), vec![0]);
+        // the third snake isn't boxed in, the pocket lies off to its side
+        assert!(chokepoints
+            .get_pockets(2)
+            .iter()
+            .all(|p| p.exit != Point::new(2, 2)));
+        assert_eq!(chokepoints.get_threat(&distances, 1), None);
+    }
+}
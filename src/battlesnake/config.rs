@@ -0,0 +1,679 @@
+//! Tunable settings read from a JSON config file
+//!
+//! The top level `strategy`, `evaluator` and `search` settings are the defaults,
+//! entries of `rulesets` override them per ruleset name. An override is merged
+//! into the defaults key by key, so only the changed values need listing:
+//!
+//! ```json
+//! {
+//!     "strategy": "minimax",
+//!     "snake": {"author": "jiricodes", "color": "#622BAA", "head": "evil", "tail": "rattle"},
+//!     "evaluator": {"weights": {"length": 1.0, "food": 1.0, "area": 0.5}},
+//!     "search": {"mode": "mixed:0.5", "policy": "learned", "max_depth": 12},
+//!     "rulesets": {
+//!         "royale": {"evaluator": {"hazard_cost": 20}},
+//!         "solo": {"strategy": "astar"}
+//!     }
+//! }
+//! ```
+//!
+//! The top level `snake` is the customization answered on `/`, fields left out
+//! keep their defaults and `version` defaults to the crate version.
+//!
+//! Entries of `personas` are further snakes served by the same process under
+//! `/<name>/`, each with its own `time_budget` in ms. The rest of a persona,
+//! `snake` included, is merged into the top level config the same way, so a
+//! persona can override settings and rulesets too. `--search` and `--strategy`
+//! overrides only apply to the snake served at the root, `--snake` takes a
+//! `<persona>.` prefix for the personas:
+//!
+//! ```json
+//! {
+//!     "personas": {
+//!         "aggro": {"snake": {"color": "#CC0000"}, "evaluator": {"weights": {"aggression": 2.0}}},
+//!         "safe": {"time_budget": 200, "strategy": "astar"}
+//!     }
+//! }
+//! ```
+//!
+//! The active config lives in a `ConfigStore`, which can reload it from disk while
+//! the server runs. Games take an `Arc` snapshot at `/start`, so a reload only
+//! affects games started after it. Personas are mounted at startup, a reload
+//! changes their settings but doesn't add or remove any.
+//!
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::sync::{Arc, Mutex, RwLock};
+
+use super::evaluator::EvaluatorConfig;
+use super::minimax::SearchParams;
+use super::strategy::StrategyKind;
+use super::SnakeProps;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub strategy: StrategyKind,
+    pub evaluator: EvaluatorConfig,
+    pub search: SearchParams,
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), String> {
+        self.evaluator.validate()?;
+        self.search.validate()
+    }
+}
+
+/// Settings per ruleset name with a fallback
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Config {
+    #[serde(flatten)]
+    default: Settings,
+    rulesets: BTreeMap<String, Settings>,
+    snake: SnakeProps,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    personas: BTreeMap<String, Persona>,
+}
+
+/// Snake served under its own URL prefix
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Persona {
+    /// search time per move in ms, the server's time budget if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_budget: Option<u64>,
+    /// shared with the games of the persona, like the active config
+    #[serde(flatten)]
+    pub config: Arc<Config>,
+}
+
+impl Persona {
+    /// Persona of `overrides` merged into the top level config `root`
+    fn from_value(root: &Value, mut overrides: Value) -> Result<Self, String> {
+        let fields = match overrides.as_object_mut() {
+            Some(fields) => fields,
+            None => return Err("Persona has to be a JSON object".to_string()),
+        };
+        let time_budget = match fields.remove("time_budget") {
+            Some(budget) => serde_json::from_value(budget).map_err(|e| e.to_string())?,
+            None => None,
+        };
+        let mut settings = root.clone();
+        merge(&mut settings, &overrides);
+        Ok(Self {
+            time_budget,
+            config: Arc::new(Config::from_value(settings)?),
+        })
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.time_budget == Some(0) {
+            return Err("Time budget has to be at least 1 ms".to_string());
+        }
+        self.config.validate()
+    }
+}
+
+impl Config {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let mut root: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let personas = match root.as_object_mut() {
+            Some(root) => root.remove("personas"),
+            None => return Err("Config has to be a JSON object".to_string()),
+        };
+        let mut config = Self::from_value(root.clone())?;
+        match personas {
+            Some(Value::Object(personas)) => {
+                for (name, overrides) in personas {
+                    let persona = Persona::from_value(&root, overrides)
+                        .map_err(|e| format!("Persona {}: {}", name, e))?;
+                    config.personas.insert(name, persona);
+                }
+            }
+            Some(Value::Null) | None => (),
+            Some(_) => return Err("Personas have to be a JSON object".to_string()),
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Config of the snake, settings and rulesets in `root`, without personas
+    fn from_value(mut root: Value) -> Result<Self, String> {
+        let (rulesets, snake) = match root.as_object_mut() {
+            Some(root) => (root.remove("rulesets"), root.remove("snake")),
+            None => return Err("Config has to be a JSON object".to_string()),
+        };
+        let snake = match snake {
+            Some(snake) => serde_json::from_value(snake).map_err(|e| format!("Snake: {}", e))?,
+            None => SnakeProps::default(),
+        };
+        let parse =
+            |value: Value| serde_json::from_value::<Settings>(value).map_err(|e| e.to_string());
+
+        let mut config = Config {
+            default: parse(root.clone())?,
+            rulesets: BTreeMap::new(),
+            snake,
+            personas: BTreeMap::new(),
+        };
+        match rulesets {
+            Some(Value::Object(rulesets)) => {
+                for (name, overrides) in rulesets {
+                    let mut settings = root.clone();
+                    merge(&mut settings, &overrides);
+                    let settings =
+                        parse(settings).map_err(|e| format!("Ruleset {}: {}", name, e))?;
+                    config.rulesets.insert(name, settings);
+                }
+            }
+            Some(Value::Null) | None => (),
+            Some(_) => return Err("Rulesets have to be a JSON object".to_string()),
+        }
+        Ok(config)
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let json = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+        Self::from_json(&json).map_err(|e| format!("Invalid config {}: {}", path, e))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.default.validate()?;
+        self.snake
+            .validate()
+            .map_err(|e| format!("Snake: {}", e))?;
+        for (name, settings) in self.rulesets.iter() {
+            settings
+                .validate()
+                .map_err(|e| format!("Ruleset {}: {}", name, e))?;
+        }
+        for (name, persona) in self.personas.iter() {
+            let valid = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid {
+                return Err(format!(
+                    "Invalid persona name \"{}\", expected letters, digits, - and _",
+                    name
+                ));
+            }
+            persona
+                .validate()
+                .map_err(|e| format!("Persona {}: {}", name, e))?;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, ruleset: &str) -> &Settings {
+        self.rulesets.get(ruleset).unwrap_or(&self.default)
+    }
+
+    pub fn get_default(&self) -> &Settings {
+        &self.default
+    }
+
+    pub fn get_snake(&self) -> &SnakeProps {
+        &self.snake
+    }
+
+    pub fn get_persona(&self, name: &str) -> Option<&Persona> {
+        self.personas.get(name)
+    }
+
+    pub fn get_personas(&self) -> &BTreeMap<String, Persona> {
+        &self.personas
+    }
+
+    /// Replaces the default settings. Rulesets and personas keep the values
+    /// they override and take everything else from the new default.
+    pub fn set_default(&mut self, settings: Settings) {
+        for persona in self.personas.values_mut() {
+            let default = rebase(&self.default, &settings, &persona.config.default);
+            Arc::make_mut(&mut persona.config).set_default(default);
+        }
+        self.set_own_default(settings);
+    }
+
+    /// Replaces the default settings of the snake at the root, rulesets follow
+    /// them the same way and personas keep theirs
+    fn set_own_default(&mut self, settings: Settings) {
+        for ruleset in self.rulesets.values_mut() {
+            *ruleset = rebase(&self.default, &settings, ruleset);
+        }
+        self.default = settings;
+    }
+
+    /// Applies `set` to the settings of `<ruleset>=<value>`, or to the default ones
+    /// without a ruleset, which the rulesets keep following whatever the order
+    fn set_override<F>(&mut self, s: &str, set: F) -> Result<(), String>
+    where
+        F: FnOnce(&mut Settings, &str) -> Result<(), String>,
+    {
+        match s.split_once('=') {
+            Some((ruleset, value)) if ruleset != "default" => {
+                let default = self.default.clone();
+                let settings = self.rulesets.entry(ruleset.to_string()).or_insert(default);
+                set(settings, value)
+            }
+            split => {
+                let value = split.map_or(s, |(_, value)| value);
+                let mut settings = self.default.clone();
+                set(&mut settings, value)?;
+                self.set_own_default(settings);
+                Ok(())
+            }
+        }
+    }
+
+    /// Sets search mode and policy from `<ruleset>=<params>` or just `<params>` for the default
+    pub fn set_search_from_str(&mut self, s: &str) -> Result<(), String> {
+        self.set_override(s, |settings, params| settings.search.set_from_str(params))
+    }
+
+    /// Sets the strategy from `<ruleset>=<name>` or just `<name>` for the default
+    pub fn set_strategy_from_str(&mut self, s: &str) -> Result<(), String> {
+        self.set_override(s, |settings, name| {
+            settings.strategy = name.parse()?;
+            Ok(())
+        })
+    }
+
+    /// Sets a snake field from `<field>=<value>`, or `<persona>.<field>=<value>` for a persona
+    pub fn set_snake_from_str(&mut self, s: &str) -> Result<(), String> {
+        let (key, value) = match s.split_once('=') {
+            Some(pair) => pair,
+            None => return Err(format!("Expected <field>=<value>, got \"{}\"", s)),
+        };
+        let (snake, field) = match key.split_once('.') {
+            Some((name, field)) => match self.personas.get_mut(name) {
+                Some(persona) => (&mut Arc::make_mut(&mut persona.config).snake, field),
+                None => return Err(format!("Unknown persona \"{}\"", name)),
+            },
+            None => (&mut self.snake, key),
+        };
+        snake.set(field, value)
+    }
+}
+
+/// Where the active config came from, kept to rebuild it on reload
+#[derive(Debug, Default)]
+struct ConfigSource {
+    path: Option<String>,
+    /// file contents at the last load, compared rather than the mtime, which
+    /// may not change on filesystems with a coarse resolution
+    contents: Option<String>,
+    /// `--search` overrides applied on top of the file
+    search: Vec<String>,
+    /// `--strategy` overrides applied on top of the file
+    strategy: Vec<String>,
+    /// `--snake` overrides applied on top of the file
+    snake: Vec<String>,
+}
+
+impl ConfigSource {
+    fn get_contents(&self) -> Option<String> {
+        let path = self.path.as_ref()?;
+        fs::read_to_string(path).ok()
+    }
+
+    fn build(&self) -> Result<Config, String> {
+        let mut config = match self.path.as_ref() {
+            Some(path) => Config::from_file(path)?,
+            None => Config::default(),
+        };
+        for search in self.search.iter() {
+            config.set_search_from_str(search)?;
+        }
+        for strategy in self.strategy.iter() {
+            config.set_strategy_from_str(strategy)?;
+        }
+        for snake in self.snake.iter() {
+            config.set_snake_from_str(snake)?;
+        }
+        Ok(config)
+    }
+}
+
+/// Holds the active config and swaps it atomically on reload
+#[derive(Debug, Default)]
+pub struct ConfigStore {
+    active: RwLock<Arc<Config>>,
+    source: Mutex<ConfigSource>,
+}
+
+impl ConfigStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads config from `path` (defaults if none) with `search`, `strategy` and `snake` overrides
+    /// and makes it active
+    pub fn load(
+        &self,
+        path: Option<&str>,
+        search: Vec<String>,
+        strategy: Vec<String>,
+        snake: Vec<String>,
+    ) -> Result<Arc<Config>, String> {
+        let mut source = ConfigSource {
+            path: path.map(|p| p.to_string()),
+            contents: None,
+            search,
+            strategy,
+            snake,
+        };
+        source.contents = source.get_contents();
+        let config = Arc::new(source.build()?);
+        *self.source.lock().unwrap() = source;
+        *self.active.write().unwrap() = config.clone();
+        Ok(config)
+    }
+
+    /// Reloads the config if the file changed since the last load.
+    /// Returns the new config, nothing if unchanged. Invalid file keeps the current config.
+    pub fn reload(&self) -> Result<Option<Arc<Config>>, String> {
+        let mut source = self.source.lock().unwrap();
+        let contents = source.get_contents();
+        if source.path.is_none() || contents == source.contents {
+            return Ok(None);
+        }
+        // remember the failed version too, so it's reported only once
+        source.contents = contents;
+        let config = Arc::new(source.build()?);
+        *self.active.write().unwrap() = config.clone();
+        Ok(Some(config))
+    }
+
+    pub fn get(&self) -> Arc<Config> {
+        self.active.read().unwrap().clone()
+    }
+
+    pub fn get_path(&self) -> Option<String> {
+        self.source.lock().unwrap().path.clone()
+    }
+}
+
+/// Recursively merges JSON objects, values in `overrides` win
+fn merge(base: &mut Value, overrides: &Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                merge(base.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+        (base, value) => *base = value.clone(),
+    }
+}
+
+/// Settings `derived` from `old` with the same overrides on top of `new`
+fn rebase(old: &Settings, new: &Settings, derived: &Settings) -> Settings {
+    let overrides = diff(
+        &serde_json::to_value(old).unwrap(),
+        &serde_json::to_value(derived).unwrap(),
+    );
+    let mut value = serde_json::to_value(new).unwrap();
+    merge(&mut value, &overrides);
+    serde_json::from_value(value).unwrap()
+}
+
+/// Values of `derived` differing from `base`, recursing into objects
+fn diff(base: &Value, derived: &Value) -> Value {
+    match (base, derived) {
+        (Value::Object(base), Value::Object(derived)) => Value::Object(
+            derived
+                .iter()
+                .filter(|(key, value)| base.get(*key) != Some(value))
+                .map(|(key, value)| match base.get(key) {
+                    Some(base) => (key.clone(), diff(base, value)),
+                    None => (key.clone(), value.clone()),
+                })
+                .collect(),
+        ),
+        (_, derived) => derived.clone(),
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match serde_json::to_string_pretty(self) {
+            Ok(json) => write!(f, "{}", json),
+            Err(_) => Err(fmt::Error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::minimax::{MovePolicy, SearchMode};
+    use super::*;
+
+    #[test]
+    fn rulesets() {
+        let config = Config::from_json(
+            r#"{
+            "evaluator": {"weights": {"length": 1.0, "food": 1.0}, "hazard_cost": 10},
+            "search": {"mode": "expectimax", "max_depth": 8},
+            "rulesets": {
+                "royale": {"evaluator": {"hazard_cost": 20}, "search": {"policy": "uniform"}},
+                "constrictor": {"strategy": "astar", "evaluator": {"weights": {"area": 1.0}}}
+            }
+        }"#,
+        )
+        .unwrap();
+        let standard = config.get("standard");
+        assert_eq!(standard.evaluator.hazard_cost, 10);
+        assert_eq!(standard.search.mode, SearchMode::Expectimax);
+        assert_eq!(standard.search.policy, MovePolicy::Learned);
+        let royale = config.get("royale");
+        assert_eq!(royale.evaluator.hazard_cost, 20);
+        assert_eq!(royale.evaluator.weights.len(), 2);
+        assert_eq!(royale.search.mode, SearchMode::Expectimax);
+        assert_eq!(royale.search.policy, MovePolicy::Uniform);
+        assert_eq!(royale.search.max_depth, Some(8));
+        // weights are merged key by key too
+        assert_eq!(config.get("constrictor").evaluator.weights.len(), 3);
+        assert_eq!(config.get("constrictor").strategy, StrategyKind::Astar);
+        assert_eq!(royale.strategy, StrategyKind::Minimax);
+        assert_eq!(Config::from_json(&format!("{}", config)).unwrap(), config);
+    }
+
+    #[test]
+    fn personas() {
+        let config = Config::from_json(
+            r##"{
+            "search": {"mode": "expectimax"},
+            "rulesets": {"royale": {"evaluator": {"hazard_cost": 20}}},
+            "snake": {"author": "someone", "head": "smile"},
+            "personas": {
+                "aggro": {"snake": {"color": "#CC0000"}, "rulesets": {"royale": {"strategy": "astar"}}},
+                "safe": {"time_budget": 200, "search": {"mode": "paranoid"}}
+            }
+        }"##,
+        )
+        .unwrap();
+        assert_eq!(config.get_personas().len(), 2);
+        assert!(config.get_persona("other").is_none());
+        let aggro = config.get_persona("aggro").unwrap();
+        assert_eq!(aggro.time_budget, None);
+        // snake fields are merged like the settings
+        let mut snake = config.get_snake().clone();
+        snake.set("color", "#CC0000").unwrap();
+        assert_eq!(aggro.config.get_snake(), &snake);
+        // personas inherit the top level settings and rulesets
+        assert_eq!(aggro.config.get("royale").evaluator.hazard_cost, 20);
+        assert_eq!(aggro.config.get("royale").strategy, StrategyKind::Astar);
+        assert_eq!(aggro.config.get("standard").search.mode, SearchMode::Expectimax);
+        let safe = config.get_persona("safe").unwrap();
+        assert_eq!(safe.time_budget, Some(200));
+        assert_eq!(safe.config.get_snake(), config.get_snake());
+        assert_eq!(safe.config.get("royale").search.mode, SearchMode::Paranoid);
+        // the top level is untouched
+        assert_eq!(config.get("royale").strategy, StrategyKind::Minimax);
+        assert_eq!(Config::from_json(&format!("{}", config)).unwrap(), config);
+
+        assert!(Config::from_json(r#"{"personas": []}"#).is_err());
+        assert!(Config::from_json(r#"{"personas": {"a/b": {}}}"#).is_err());
+        assert!(Config::from_json(r#"{"personas": {"": {}}}"#).is_err());
+        assert!(Config::from_json(r#"{"personas": {"safe": {"time_budget": 0}}}"#).is_err());
+        assert!(Config::from_json(r#"{"personas": {"safe": {"snake": {"colour": "red"}}}}"#).is_err());
+        assert!(Config::from_json(r#"{"personas": {"safe": {"strategy": "greedy"}}}"#).is_err());
+        assert!(Config::from_json(r#"{"personas": {"safe": {"snake": {"color": "red"}}}}"#).is_err());
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Config::from_json("[]").is_err());
+        assert!(Config::from_json(r#"{"evaluator": {"weights": {"luck": 1.0}}}"#).is_err());
+        assert!(Config::from_json(r#"{"search": {"mode": "greedy"}}"#).is_err());
+        assert!(Config::from_json(r#"{"serach": {}}"#).is_err());
+        assert!(Config::from_json(r#"{"strategy": "greedy"}"#).is_err());
+        assert!(Config::from_json(r##"{"snake": {"color": "#62BAA"}}"##).is_err());
+        assert!(Config::from_json(r#"{"rulesets": {"royale": {"snake": {}}}}"#).is_err());
+        assert!(
+            Config::from_json(r#"{"rulesets": {"royale": {"search": {"max_depth": 0}}}}"#).is_err()
+        );
+        assert!(Config::from_file("/nonexistent/config.json").is_err());
+        assert_eq!(Config::from_json("{}").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn reload() {
+        let path =
+            std::env::temp_dir().join(format!("battlesnake-config-{}.json", std::process::id()));
+        let path_str = path.to_str().unwrap();
+        fs::write(&path, r#"{"search": {"mode": "expectimax"}}"#).unwrap();
+        let store = ConfigStore::new();
+        assert_eq!(*store.get(), Config::default());
+        let loaded = store
+            .load(
+                Some(path_str),
+                vec!["royale=paranoid".to_string()],
+                vec!["royale=astar".to_string()],
+                vec!["color=#000000".to_string()],
+            )
+            .unwrap();
+        assert_eq!(loaded.get("standard").search.mode, SearchMode::Expectimax);
+        assert_eq!(store.reload(), Ok(None));
+
+        // snapshot taken before the reload stays as it was
+        let snapshot = store.get();
+        fs::write(&path, r#"{"search": {"mode": "mixed:0.5"}}"#).unwrap();
+        assert!(store.reload().unwrap().is_some());
+        assert_eq!(snapshot.get("standard").search.mode, SearchMode::Expectimax);
+        assert_eq!(
+            store.get().get("standard").search.mode,
+            SearchMode::Mixed(0.5)
+        );
+        // cli overrides survive the reload
+        assert_eq!(store.get().get("royale").search.mode, SearchMode::Paranoid);
+        assert_eq!(store.get().get("royale").strategy, StrategyKind::Astar);
+        assert!(format!("{}", store.get()).contains("#000000"));
+
+        fs::write(&path, r#"{"search": {"mode": "greedy"}}"#).unwrap();
+        assert!(store.reload().is_err());
+        assert_eq!(
+            store.get().get("standard").search.mode,
+            SearchMode::Mixed(0.5)
+        );
+        assert_eq!(store.reload(), Ok(None));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn set_default() {
+        let mut config = Config::from_json(
+            r#"{
+            "evaluator": {"weights": {"length": 1.0, "food": 1.0}},
+            "rulesets": {
+                "royale": {"evaluator": {"weights": {"food": 2.0}, "hazard_cost": 20}},
+                "solo": {"strategy": "astar"}
+            },
+            "personas": {"safe": {"evaluator": {"weights": {"length": 3.0}}}}
+        }"#,
+        )
+        .unwrap();
+        let mut tuned = config.get_default().clone();
+        tuned.evaluator.weights.insert("length".to_string(), 0.5);
+        tuned.evaluator.weights.insert("food".to_string(), 1.5);
+        config.set_default(tuned);
+        let weights = |settings: &Settings| {
+            let w = &settings.evaluator.weights;
+            (w["length"], w["food"])
+        };
+        assert_eq!(weights(config.get("standard")), (0.5, 1.5));
+        assert_eq!(weights(config.get("solo")), (0.5, 1.5));
+        assert_eq!(config.get("solo").strategy, StrategyKind::Astar);
+        // overridden values stay
+        assert_eq!(weights(config.get("royale")), (0.5, 2.0));
+        assert_eq!(config.get("royale").evaluator.hazard_cost, 20);
+        let safe = &config.get_persona("safe").unwrap().config;
+        assert_eq!(weights(safe.get("standard")), (3.0, 1.5));
+        assert_eq!(weights(safe.get("royale")), (3.0, 2.0));
+    }
+
+    #[test]
+    fn snake_from_str() {
+        let mut config = Config::from_json(r#"{"personas": {"aggro": {}}}"#).unwrap();
+        assert_eq!(config.get_snake(), &SnakeProps::default());
+        config.set_snake_from_str("head=smile").unwrap();
+        config.set_snake_from_str("aggro.color=#CC0000").unwrap();
+        assert_ne!(config.get_snake(), &SnakeProps::default());
+        let aggro = config.get_persona("aggro").unwrap().config.get_snake();
+        assert_ne!(aggro, config.get_snake());
+        assert!(config.set_snake_from_str("safe.color=#CC0000").is_err());
+        assert!(config.set_snake_from_str("color").is_err());
+        assert!(config.set_snake_from_str("color=#CC00").is_err());
+        assert!(config.set_snake_from_str("horns=spiky").is_err());
+        assert_eq!(Config::from_json(&format!("{}", config)).unwrap(), config);
+    }
+
+    #[test]
+    fn search_from_str() {
+        let mut config = Config::default();
+        assert_eq!(config.get("royale").search, SearchParams::default());
+        config
+            .set_search_from_str("royale=expectimax/uniform")
+            .unwrap();
+        config.set_search_from_str("mixed:0.25/softmax:2").unwrap();
+        assert_eq!(config.get("royale").search.mode, SearchMode::Expectimax);
+        assert_eq!(config.get("royale").search.policy, MovePolicy::Uniform);
+        assert_eq!(config.get("standard").search.mode, SearchMode::Mixed(0.25));
+        assert_eq!(
+            config.get("standard").search.policy,
+            MovePolicy::Softmax(2.0)
+        );
+        assert!(config.set_search_from_str("royale=mixed:1.5").is_err());
+        assert!(config.set_search_from_str("greedy").is_err());
+
+        config.set_strategy_from_str("solo=astar").unwrap();
+        assert_eq!(config.get("solo").strategy, StrategyKind::Astar);
+        assert_eq!(config.get("standard").strategy, StrategyKind::Minimax);
+        assert!(config.set_strategy_from_str("greedy").is_err());
+    }
+
+    #[test]
+    fn overrides_any_order() {
+        let mut config = Config::from_json(
+            r#"{"rulesets": {"royale": {"evaluator": {"hazard_cost": 20}}}}"#,
+        )
+        .unwrap();
+        config.set_search_from_str("solo=expectimax/uniform").unwrap();
+        config.set_strategy_from_str("astar").unwrap();
+        config.set_search_from_str("mixed:0.25/softmax:2").unwrap();
+        config.set_strategy_from_str("default=minimax").unwrap();
+        config.set_strategy_from_str("astar").unwrap();
+        // default level overrides reach the rulesets given before them
+        assert_eq!(config.get("solo").strategy, StrategyKind::Astar);
+        assert_eq!(config.get("royale").strategy, StrategyKind::Astar);
+        assert_eq!(config.get("royale").search.mode, SearchMode::Mixed(0.25));
+        assert_eq!(config.get("royale").evaluator.hazard_cost, 20);
+        // and don't replace what the rulesets override themselves
+        assert_eq!(config.get("solo").search.mode, SearchMode::Expectimax);
+        assert_eq!(config.get("solo").search.policy, MovePolicy::Uniform);
+        assert_eq!(config.get("standard").search.policy, MovePolicy::Softmax(2.0));
+    }
+}
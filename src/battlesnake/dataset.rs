@@ -0,0 +1,257 @@
+//! Training dataset export
+//!
+//! Converts recorded games, our own as well as self-play, into fixed-size
+//! samples of a single board size. Every move record of a finished game is
+//! one sample. The dataset is written as NPY files (NumPy format version 1.0,
+//! little endian, C order) sharing the sample count `N`:
+//!
+//! | file | dtype | shape | content |
+//! |------|-------|-------|---------|
+//! | `planes.npy` | `<f4` | `(N, 8, height, width)` | board planes of `planes::encode` |
+//! | `legal.npy` | `\|u1` | `(N, 4)` | 1 for moves not running into a wall or body |
+//! | `chosen.npy` | `\|u1` | `(N,)` | index of the move made |
+//! | `scores.npy` | `<f4` | `(N, 4)` | search scores of the moves, NaN if not searched |
+//! | `outcome.npy` | `<f4` | `(N,)` | 1.0 win, 0.5 draw, 0.0 loss |
+//!
+//! Moves are indexed by `Direction::as_index`: right, left, up, down.
+//!
+use std::fs;
+use std::path::Path;
+
+use super::planes::{self, PLANES};
+use super::replay::{Replay, ReplayRecord};
+use super::Board;
+use super::Direction;
+use super::GameInfo;
+use super::ALL_DIRECTIONS;
+
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub planes: Vec<f32>,
+    pub legal: [bool; 4],
+    pub chosen: Direction,
+    pub scores: [Option<f32>; 4],
+    pub outcome: f32,
+}
+
+impl Sample {
+    pub fn new(
+        state: &GameInfo,
+        chosen: Direction,
+        scores: [Option<f32>; 4],
+        outcome: f32,
+    ) -> Self {
+        let board = Board::from_api(state);
+        let moves = board.get_pruned_moves(&board.snakes[0].head(), 1);
+        let mut legal = [false; 4];
+        for dir in ALL_DIRECTIONS.iter().filter(|dir| moves.contains(dir)) {
+            legal[dir.as_index()] = true;
+        }
+        Self {
+            planes: planes::encode(&board, 0, state.get_hazards()),
+            legal,
+            chosen,
+            scores,
+            outcome,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Dataset {
+    pub width: usize,
+    pub height: usize,
+    pub samples: Vec<Sample>,
+}
+
+impl Dataset {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Adds moves of a finished game on a board of the dataset's size,
+    /// returns the number of samples added
+    pub fn add_replay(&mut self, replay: &Replay) -> usize {
+        let outcome = match replay.get_outcome() {
+            Some(outcome) => outcome,
+            None => return 0,
+        };
+        let before = self.samples.len();
+        for record in replay.records.iter() {
+            if let ReplayRecord::Move {
+                state,
+                chosen,
+                scores,
+            } = record
+            {
+                if state.get_board_dimensions() == (self.height, self.width) {
+                    self.samples
+                        .push(Sample::new(state, *chosen, *scores, outcome));
+                }
+            }
+        }
+        self.samples.len() - before
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Writes the dataset's NPY files to `dir`
+    pub fn write(&self, dir: &str) -> Result<(), String> {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir, e))?;
+        let dir = Path::new(dir);
+        let n = self.samples.len();
+        let f32_bytes = |values: &mut dyn Iterator<Item = f32>| -> Vec<u8> {
+            values.flat_map(|v| v.to_le_bytes().to_vec()).collect()
+        };
+
+        let planes = f32_bytes(&mut self.samples.iter().flat_map(|s| s.planes.iter().cloned()));
+        write_npy(
+            &dir.join("planes.npy"),
+            "<f4",
+            &[n, PLANES, self.height, self.width],
+            &planes,
+        )?;
+        let legal: Vec<u8> = self
+            .samples
+            .iter()
+            .flat_map(|s| s.legal.iter().map(|l| *l as u8))
+            .collect();
+        write_npy(&dir.join("legal.npy"), "|u1", &[n, 4], &legal)?;
+        let chosen: Vec<u8> = self
+            .samples
+            .iter()
+            .map(|s| s.chosen.as_index() as u8)
+            .collect();
+        write_npy(&dir.join("chosen.npy"), "|u1", &[n], &chosen)?;
+        let scores = f32_bytes(
+            &mut self
+                .samples
+                .iter()
+                .flat_map(|s| s.scores.iter().map(|v| v.unwrap_or(f32::NAN))),
+        );
+        write_npy(&dir.join("scores.npy"), "<f4", &[n, 4], &scores)?;
+        let outcome = f32_bytes(&mut self.samples.iter().map(|s| s.outcome));
+        write_npy(&dir.join("outcome.npy"), "<f4", &[n], &outcome)
+    }
+}
+
+/// Writes `data` as an NPY version 1.0 array
+fn write_npy(path: &Path, descr: &str, shape: &[usize], data: &[u8]) -> Result<(), String> {
+    let shape: Vec<String> = shape.iter().map(|s| s.to_string()).collect();
+    let shape = match shape.len() {
+        1 => format!("({},)", shape[0]),
+        _ => format!("({})", shape.join(", ")),
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        descr, shape
+    );
+    // magic, version and header length take 10 bytes, data starts 64 byte aligned
+    let padding = 63 - (10 + header.len()) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+
+    let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    bytes.extend_from_slice(data);
+    fs::write(path, bytes).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn state(width: usize) -> GameInfo {
+        GameInfo::new(&format!(
+            r#"{{
+            "game": {{"id": "game-1", "timeout": 500}},
+            "turn": 3,
+            "board": {{
+                "height": 7,
+                "width": {},
+                "food": [{{"x": 4, "y": 4}}],
+                "hazards": [],
+                "snakes": [
+                    {{"id": "me", "name": "me", "health": 90, "body": [{{"x": 0, "y": 1}}, {{"x": 0, "y": 0}}, {{"x": 1, "y": 0}}], "head": {{"x": 0, "y": 1}}, "length": 3, "shout": ""}},
+                    {{"id": "other", "name": "other", "health": 90, "body": [{{"x": 1, "y": 2}}, {{"x": 2, "y": 2}}, {{"x": 3, "y": 2}}], "head": {{"x": 1, "y": 2}}, "length": 3, "shout": ""}}
+                ]
+            }},
+            "you": {{"id": "me", "name": "me", "health": 90, "body": [{{"x": 0, "y": 1}}, {{"x": 0, "y": 0}}, {{"x": 1, "y": 0}}], "head": {{"x": 0, "y": 1}}, "length": 3, "shout": ""}}
+        }}"#,
+            width
+        ))
+    }
+
+    fn replay(width: usize, end: bool) -> Replay {
+        let mut records = vec![
+            ReplayRecord::Move {
+                state: state(width),
+                chosen: Direction::Up,
+                scores: [Some(0.4), None, Some(0.6), None],
+            };
+            2
+        ];
+        if end {
+            let mut state = state(width);
+            state.board.snakes.remove(1);
+            records.push(ReplayRecord::End { state });
+        }
+        Replay { records }
+    }
+
+    #[test]
+    fn samples() {
+        let mut dataset = Dataset::new(7, 7);
+        assert_eq!(dataset.add_replay(&replay(7, false)), 0);
+        assert_eq!(dataset.add_replay(&replay(9, true)), 0);
+        assert_eq!(dataset.add_replay(&replay(7, true)), 2);
+        let sample = &dataset.samples[0];
+        assert_eq!(sample.planes.len(), PLANES * 49);
+        // wall on the left, own body below
+        assert_eq!(sample.legal, [true, false, true, false]);
+        assert_eq!(sample.chosen, Direction::Up);
+        assert_eq!(sample.outcome, 1.0);
+    }
+
+    #[test]
+    fn npy() {
+        let mut dataset = Dataset::new(7, 7);
+        dataset.add_replay(&replay(7, true));
+        let dir = std::env::temp_dir().join(format!("battlesnake-dataset-{}", std::process::id()));
+        dataset.write(dir.to_str().unwrap()).unwrap();
+
+        let read = |name: &str| {
+            let bytes = fs::read(dir.join(name)).unwrap();
+            assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+            let len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+            assert_eq!((10 + len) % 64, 0);
+            let header = String::from_utf8(bytes[10..10 + len].to_vec()).unwrap();
+            (header, bytes[10 + len..].to_vec())
+        };
+        let (header, data) = read("planes.npy");
+        assert!(header.contains("'shape': (2, 8, 7, 7)"));
+        assert_eq!(data.len(), 2 * PLANES * 49 * 4);
+        let (header, data) = read("chosen.npy");
+        assert!(header.contains("'descr': '|u1'") && header.contains("'shape': (2,)"));
+        assert_eq!(data, vec![2, 2]);
+        let (_, data) = read("scores.npy");
+        let second = f32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+        assert!(second.is_nan());
+        let (_, data) = read("legal.npy");
+        assert_eq!(data[..4], [1, 0, 1, 0]);
+        let (_, data) = read("outcome.npy");
+        assert_eq!(data[..4], 1.0f32.to_le_bytes());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
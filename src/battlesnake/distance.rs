@@ -0,0 +1,249 @@
+//! Distance maps from snake heads
+//!
+//! A single breadth first search started from all heads at once gives every
+//! snake its number of steps to each cell it can reach. The path cost, where
+//! hazard tiles cost `hazard_cost` instead of 1, comes from a Dijkstra search
+//! per snake, as a detour around hazards can be cheaper than the fastest way.
+//! Along with it goes the number of steps of that cheapest way, and the search
+//! from the heads keeps the cost of the cheapest of the fastest ways.
+//! Body cells are entered only once vacated, same as in the flood fill. The
+//! maps are computed once per board and shared by food access, aggression,
+//! area and territory evaluation.
+//!
+use super::pathfinding::{dijkstra, Cost, Time};
+use super::Board;
+use super::Point;
+
+#[derive(Debug, Clone)]
+pub struct DistanceMaps {
+    width: usize,
+    height: usize,
+    /// per snake, steps from its head to each cell
+    steps: Vec<Vec<Option<usize>>>,
+    /// per snake, path cost of the cheapest fastest way to each cell
+    fastest: Vec<Vec<Option<usize>>>,
+    /// per snake, path cost and steps of the cheapest way to each cell
+    costs: Vec<Vec<Option<(usize, usize)>>>,
+}
+
+impl DistanceMaps {
+    fn get_cell(&self, p: &Point) -> Option<usize> {
+        if p.x < 0 || p.y < 0 || p.x as usize >= self.width || p.y as usize >= self.height {
+            None
+        } else {
+            Some(p.y as usize * self.width + p.x as usize)
+        }
+    }
+
+    /// Steps snake `index` needs to get to `p`, none if it can't
+    pub fn get_steps(&self, index: usize, p: &Point) -> Option<usize> {
+        self.get_cell(p).and_then(|c| self.steps[index][c])
+    }
+
+    /// Path cost of the cheapest way of snake `index` to `p`
+    pub fn get_cost(&self, index: usize, p: &Point) -> Option<usize> {
+        self.get_cheapest(index, p).map(|(cost, _)| cost)
+    }
+
+    /// Path cost and steps of the cheapest of the fastest ways of snake `index` to `p`
+    pub fn get_fastest(&self, index: usize, p: &Point) -> Option<(usize, usize)> {
+        let c = self.get_cell(p)?;
+        Some((self.fastest[index][c]?, self.steps[index][c]?))
+    }
+
+    /// Path cost and steps of the cheapest way of snake `index` to `p`
+    pub fn get_cheapest(&self, index: usize, p: &Point) -> Option<(usize, usize)> {
+        self.get_cell(p).and_then(|c| self.costs[index][c])
+    }
+
+    /// Steps of snake `index` to each cell, row-major
+    pub fn get_steps_map(&self, index: usize) -> &[Option<usize>] {
+        &self.steps[index]
+    }
+
+    /// Number of cells snake `index` can reach, its head excluded
+    pub fn get_reachable(&self, index: usize) -> usize {
+        self.steps[index]
+            .iter()
+            .filter(|s| matches!(s, Some(steps) if *steps > 0))
+            .count()
+    }
+}
+
+/// Steps on cells once vacated, hazards cost more
+struct VacateCost<'a> {
+    board: &'a Board,
+    times: &'a [usize],
+    step_costs: &'a [usize],
+}
+
+impl<'a> Cost for VacateCost<'a> {
+    fn step(&self, _from: &Point, to: &Point, time: Time) -> Option<usize> {
+        if !self.board.is_inbounds(to) {
+            return None;
+        }
+        let j = self.board.get_index(to);
+        if self.times[j] > time.turn {
+            None
+        } else {
+            Some(self.step_costs[j])
+        }
+    }
+}
+
+impl Board {
+    pub fn get_distance_maps(&self, hazards: &[Point], hazard_cost: usize) -> DistanceMaps {
+        let times = self.get_vacate_times();
+        let n_snakes = self.snakes.len();
+        let mut step_costs = vec![1; times.len()];
+        for hazard in hazards.iter().filter(|p| self.is_inbounds(p)) {
+            step_costs[self.get_index(hazard)] = hazard_cost;
+        }
+        let mut steps = vec![vec![None; times.len()]; n_snakes];
+        let mut fastest = vec![vec![None; times.len()]; n_snakes];
+
+        // (cell, snake) pairs reached on the current turn
+        let mut frontier: Vec<(usize, usize)> = Vec::new();
+        for (i, snake) in self.snakes.iter().enumerate() {
+            if self.is_inbounds(&snake.head()) {
+                let c = self.get_index(&snake.head());
+                steps[i][c] = Some(0);
+                fastest[i][c] = Some(0);
+                frontier.push((c, i));
+            }
+        }
+        let mut turn = 0;
+        while !frontier.is_empty() {
+            let mut next: Vec<(usize, usize)> = Vec::new();
+            for (c, i) in frontier.iter() {
+                for n in self.get_point(*c).get_neighbours() {
+                    if !self.is_inbounds(&n) {
+                        continue;
+                    }
+                    let j = self.get_index(&n);
+                    if times[j] > turn + 1 {
+                        continue;
+                    }
+                    let cost = fastest[*i][*c].map(|cost| cost + step_costs[j]);
+                    match steps[*i][j] {
+                        None => {
+                            steps[*i][j] = Some(turn + 1);
+                            fastest[*i][j] = cost;
+                            next.push((j, *i));
+                        }
+                        // reached this turn already, keep the cheaper way
+                        Some(s) if s == turn + 1 => fastest[*i][j] = fastest[*i][j].min(cost),
+                        _ => (),
+                    }
+                }
+            }
+            frontier = next;
+            turn += 1;
+        }
+
+        // without hazards to go around, the fastest ways are the cheapest
+        let costs = if step_costs.iter().all(|c| *c == 1) {
+            steps
+                .iter()
+                .map(|map| map.iter().map(|s| s.map(|s| (s, s))).collect())
+                .collect()
+        } else {
+            let cost = VacateCost {
+                board: self,
+                times: &times,
+                step_costs: &step_costs,
+            };
+            self.snakes
+                .iter()
+                .map(|snake| {
+                    let mut map = vec![None; times.len()];
+                    if self.is_inbounds(&snake.head()) {
+                        for (p, (c, time)) in dijkstra(&cost, &[snake.head()]) {
+                            map[self.get_index(&p)] = Some((c, time.turn));
+                        }
+                    }
+                    map
+                })
+                .collect()
+        };
+
+        DistanceMaps {
+            width: self.get_width(),
+            height: self.get_height(),
+            steps,
+            fastest,
+            costs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::GameInfo;
+    use super::*;
+
+    #[test]
+    fn maps() {
+        let board = Board::from_api(&GameInfo::new(
+            r#"{
+            "game": {"id": "game-1", "timeout": 500},
+            "turn": 5,
+            "board": {
+                "height": 5,
+                "width": 5,
+                "food": [],
+                "hazards": [],
+                "snakes": [
+                    {"id": "me", "name": "me", "health": 90, "body": [{"x": 0, "y": 0}, {"x": 0, "y": 1}], "head": {"x": 0, "y": 0}, "length": 2, "shout": ""},
+                    {"id": "other", "name": "other", "health": 90, "body": [{"x": 2, "y": 4}, {"x": 2, "y": 3}, {"x": 2, "y": 2}, {"x": 2, "y": 1}], "head": {"x": 2, "y": 4}, "length": 4, "shout": ""}
+                ]
+            },
+            "you": {"id": "me", "name": "me", "health": 90, "body": [{"x": 0, "y": 0}, {"x": 0, "y": 1}], "head": {"x": 0, "y": 0}, "length": 2, "shout": ""}
+        }"#,
+        ));
+        let hazards = vec![Point::new(1, 0), Point::new(1, 1)];
+        let maps = board.get_distance_maps(&hazards, 10);
+        assert_eq!(maps.get_steps(0, &Point::new(0, 0)), Some(0));
+        // the other snake's tail at (2, 1) is gone by the time we get there
+        assert_eq!(maps.get_steps(0, &Point::new(2, 1)), Some(3));
+        assert_eq!(maps.get_steps(0, &Point::new(4, 0)), Some(4));
+        // both ways to (1, 1) take 2 steps, the one through a single hazard is cheaper
+        assert_eq!(maps.get_cost(0, &Point::new(1, 1)), Some(11));
+        assert_eq!(maps.get_cost(0, &Point::new(0, 2)), Some(2));
+        assert_eq!(maps.get_steps(1, &Point::new(0, 4)), Some(2));
+        assert_eq!(maps.get_steps(1, &Point::new(5, 0)), None);
+        assert_eq!(maps.get_reachable(0), 24);
+        assert_eq!(maps.get_reachable(0), board.flood_fill(0));
+        assert_eq!(maps.get_reachable(1), board.flood_fill(1));
+    }
+
+    #[test]
+    fn hazard_detour() {
+        // a row of hazards between us and the right edge
+        let board = Board::from_api(&GameInfo::new(
+            r#"{
+            "game": {"id": "game-1", "timeout": 500},
+            "turn": 5,
+            "board": {
+                "height": 5,
+                "width": 5,
+                "food": [],
+                "hazards": [],
+                "snakes": [
+                    {"id": "me", "name": "me", "health": 90, "body": [{"x": 0, "y": 2}, {"x": 0, "y": 1}, {"x": 0, "y": 0}], "head": {"x": 0, "y": 2}, "length": 3, "shout": ""}
+                ]
+            },
+            "you": {"id": "me", "name": "me", "health": 90, "body": [{"x": 0, "y": 2}, {"x": 0, "y": 1}, {"x": 0, "y": 0}], "head": {"x": 0, "y": 2}, "length": 3, "shout": ""}
+        }"#,
+        ));
+        let hazards = vec![Point::new(1, 2), Point::new(2, 2), Point::new(3, 2)];
+        let maps = board.get_distance_maps(&hazards, 10);
+        // going straight takes 4 steps through 3 hazards, around them 6 plain steps
+        assert_eq!(maps.get_steps(0, &Point::new(4, 2)), Some(4));
+        assert_eq!(maps.get_cost(0, &Point::new(4, 2)), Some(6));
+        assert_eq!(maps.get_cheapest(0, &Point::new(4, 2)), Some((6, 6)));
+        assert_eq!(maps.get_fastest(0, &Point::new(4, 2)), Some((31, 4)));
+        assert_eq!(maps.get_cost(0, &Point::new(2, 2)), Some(13));
+        assert_eq!(board.get_distance_maps(&[], 10).get_cost(0, &Point::new(4, 2)), Some(4));
+    }
+}
@@ -1,9 +1,13 @@
-use super::astar::Astar;
+use super::config::Settings;
+use super::foodrace::FoodRace;
 use super::grid::GameGrid;
+use super::hamilton::HamiltonCycle;
+use super::spacefill::SurvivalSolver;
 use super::grid::GridObject;
-use super::heuristic::{HeurMethod, Heuristic};
 use super::input::GameInfo;
+use super::pathfinding::{astar, GridCost, Occupancy};
 use super::point::Point;
+use super::Board;
 use super::Dfs;
 use super::Direction;
 
@@ -19,9 +23,7 @@ pub struct Move {
 }
 
 impl Move {
-    pub fn new(input: &str) -> Self {
-        // Parse game information
-        let gameinfo = GameInfo::new(&input);
+    pub fn new(gameinfo: &GameInfo, settings: &Settings) -> Self {
         // Create grid and fill it with snake bodies, hazard and food - Should be split
         let mut grid = GameGrid::new(gameinfo.get_board_dimensions());
         grid.set_snakes(gameinfo.get_snake_bodies());
@@ -46,82 +48,98 @@ impl Move {
 
         // If length is under 8 the snake cannot trap itself
         // so lets just head towards closest food
-        let mut heur = Heuristic::new(HeurMethod::Battlesnake);
+        // bodies become passable as they move out of the way
+        let occupancy = Occupancy::new(
+            grid.get_width(),
+            grid.get_height(),
+            &gameinfo.get_snake_bodies(),
+            &food,
+        );
+        // give up on apples an enemy gets to first, unless there is nothing else
+        let board = Board::from_api(gameinfo);
+        let hazard_cost = settings.evaluator.hazard_cost;
+        let distances = board.get_distance_maps(hazards, hazard_cost);
+        let mut targets = FoodRace::new(&board, &distances, hazards, hazard_cost).get_targets(0);
+        if targets.is_empty() {
+            targets = food.clone();
+        }
         let mut move_point = Point::new(0, 0);
         let mut path = None;
-        let mut astar = Astar::new();
         let mut best = std::f32::MAX;
-        for apple in &food {
-            heur.battlesnake_init(
-                grid.get_width(),
-                grid.get_height(),
-                &hazards,
-                &head_collision,
-                hp,
-                apple,
-            );
-            if astar.solve(head, *apple, &grid, &heur) {
-                let c = astar.get_cost();
+        for apple in &targets {
+            let cost = GridCost::new(&grid, hazard_cost).with_occupancy(&occupancy, gameinfo.get_my_index());
+            if let Some((c, found)) = astar(&cost, head, *apple) {
+                let c = c as f32;
                 let mut dfs = Dfs::new();
                 let ret = dfs.get_atleast_len(apple, &grid, my_len);
                 // println!("[{}]  Return for Apple {}: {}", sym, apple, ret);
-                // need to involve checking, wheather I survive the way back
-                if (c <= hp as f32 || head.is_neighbour(*apple)) && c < best && ret {
-                    path = Some(astar.get_path());
+                // the first step has to leave a way back to our tail
+                let safe = found
+                    .nodes
+                    .iter()
+                    .rev()
+                    .nth(1)
+                    .and_then(|p| head.get_neighbour_direction(*p))
+                    .is_some_and(|dir| board.is_safe_move(0, dir));
+                if (c <= hp as f32 || head.is_neighbour(*apple)) && c < best && ret && safe {
+                    path = Some(found);
                     best = c;
                 }
             }
         }
-        // Run the algo again, but ignore hazard
-        // if path.is_none() {
-        //     grid.ignore_hazard();
-        //     for apple in &food {
-        //         path = Astar::solve(head, *apple, &grid, Heuristic::new(HeurMethod::Manhattan));
-        //         if path.is_some() {
-        //             break;
-        //         }
-        //     }
-        // }
-
         if path.is_some() {
             // println!("[{}] Found path with cost {}", sym, best);
-            move_point = path.unwrap()[0];
+            // path goes from the apple back to the head
+            move_point = path.unwrap().nodes.iter().rev().nth(1).cloned().unwrap_or(head);
         } else {
             // println!("[{}] A* found no path, moving first free space", sym);
             // Otherwise
-            // if solo game -> we do hamilton
-            // else -> super trooper algo?
+            // solo game -> follow the hamilton cycle
+            // else -> survive as long as the space allows
+            let cycle = HamiltonCycle::new(grid.get_width(), grid.get_height())
+                .filter(|_| board.snakes.len() == 1);
+            let fill = cycle
+                .and_then(|cycle| cycle.get_move(&board, 0))
+                .or_else(|| {
+                    SurvivalSolver::new(settings.evaluator.hazard_damage)
+                        .solve(&board, 0, hazards)
+                        .map(|(dir, _)| dir)
+                });
 
-            // Supersimple, based on empty
-            move_point = head.get_right();
-            let turns = head.get_neighbours();
-            for point in &turns {
-                let val = grid.get_value(point);
-                if val.is_considerable() {
-                    let mut dfs = Dfs::new();
-                    let ret = dfs.get_atleast_len(point, &grid, my_len);
-                    // println!("[{}] Return for Consider Point {}: {}", sym, point, ret);
-                    if ret {
-                        move_point = *point;
-                        break;
-                    }
-                } else if val.is_accessible() {
-                    let mut dfs = Dfs::new();
-                    let ret = dfs.get_atleast_len(point, &grid, my_len);
-                    // println!("[{}] Return for Access Point {}: {}", sym, point, ret);
-                    if ret {
-                        move_point = *point;
+            if let Some(dir) = fill {
+                move_point = head + dir;
+            } else {
+                // Supersimple, based on empty
+                move_point = head.get_right();
+                let turns = head.get_neighbours();
+                for point in &turns {
+                    let val = grid.get_value(point);
+                    if val.is_considerable() {
+                        let mut dfs = Dfs::new();
+                        let ret = dfs.get_atleast_len(point, &grid, my_len);
+                        // println!("[{}] Return for Consider Point {}: {}", sym, point, ret);
+                        if ret {
+                            move_point = *point;
+                            break;
+                        }
+                    } else if val.is_accessible() {
+                        let mut dfs = Dfs::new();
+                        let ret = dfs.get_atleast_len(point, &grid, my_len);
+                        // println!("[{}] Return for Access Point {}: {}", sym, point, ret);
+                        if ret {
+                            move_point = *point;
+                        }
                     }
                 }
-            }
-            if grid.get_value(&move_point) == GridObject::Outofbounds {
-                // println!("[{}] Selected point ({}) is out of bounds, searching for inbound one. in [{}, {}, {}, {}]", sym, move_point, turns[0], turns[1], turns[2], turns[3]);
-                for p in &turns {
-                    let v = grid.get_value(p);
-                    if v != GridObject::Outofbounds {
-                        move_point = *p;
-                        if v.is_accessible() {
-                            break;
+                if grid.get_value(&move_point) == GridObject::Outofbounds {
+                    // println!("[{}] Selected point ({}) is out of bounds, searching for inbound one. in [{}, {}, {}, {}]", sym, move_point, turns[0], turns[1], turns[2], turns[3]);
+                    for p in &turns {
+                        let v = grid.get_value(p);
+                        if v != GridObject::Outofbounds {
+                            move_point = *p;
+                            if v.is_accessible() {
+                                break;
+                            }
                         }
                     }
                 }
@@ -148,7 +166,7 @@ impl Move {
     }
 
     pub fn as_option_string(input: &str) -> Option<String> {
-        Some(serde_json::to_string(&Move::new(&input)).unwrap())
+        Some(serde_json::to_string(&Move::new(&GameInfo::new(input), &Settings::default())).unwrap())
     }
 
     pub fn get_json_string(&self) -> String {
@@ -0,0 +1,613 @@
+//! Board evaluation for the search
+//!
+//! An `Evaluator` scores a board from the point of view of one snake. The
+//! `WeightedEvaluator` combines named components, each scoring 0.0 - 1.0,
+//! into a weighted average so strategies can be assembled from parts
+//! and compared without touching the search itself.
+//!
+use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::board::HAZARD_COST;
+use super::chokepoint::Chokepoints;
+use super::distance::DistanceMaps;
+use super::foodrace::{FoodRace, Race};
+use super::tactics::KillMove;
+use super::territory::Territory;
+use super::Board;
+use super::Direction;
+use super::Point;
+
+/// Score of a board where the snake is the last one standing
+pub const WIN_SCORE: f32 = 2.0;
+/// Health lost per turn in hazard on top of the regular turn
+const HAZARD_DAMAGE: f32 = 15.0;
+/// Components and their weights used unless configured otherwise
+const DEFAULT_WEIGHTS: [(&str, f32); 7] = [
+    ("length", 1.0),
+    ("food", 1.0),
+    ("area", 0.5),
+    ("territory", 0.3),
+    ("aggression", 0.2),
+    ("hazard", 0.3),
+    ("tactics", 0.2),
+];
+
+pub trait Evaluator: Send + Sync {
+    /// Score of `board` for snake `index`, 0.0 - 1.0 while the game goes on
+    fn evaluate(&self, board: &Board, index: usize, hazards: &[Point]) -> f32;
+
+    /// Score as by `evaluate` along with the kill moves of snake `index`,
+    /// if the evaluation found them anyway
+    fn evaluate_with_kills(
+        &self,
+        board: &Board,
+        index: usize,
+        hazards: &[Point],
+    ) -> (f32, Option<Vec<Direction>>) {
+        (self.evaluate(board, index, hazards), None)
+    }
+}
+
+pub trait Component: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Score of the position for snake `index` in 0.0 - 1.0
+    fn score(&self, position: &Position, index: usize) -> f32;
+}
+
+/// Board being evaluated along with analyses shared by the components
+pub struct Position<'a> {
+    pub board: &'a Board,
+    pub hazards: &'a [Point],
+    pub distances: DistanceMaps,
+    pub food_race: FoodRace,
+    /// found on first use, few components need them
+    chokepoints: OnceCell<Chokepoints>,
+    /// kill moves of each snake, found on first use
+    kills: OnceCell<Vec<Vec<KillMove>>>,
+}
+
+impl<'a> Position<'a> {
+    pub fn new(board: &'a Board, hazards: &'a [Point], hazard_cost: usize) -> Self {
+        let distances = board.get_distance_maps(hazards, hazard_cost);
+        let food_race = FoodRace::new(board, &distances, hazards, hazard_cost);
+        Self {
+            board,
+            hazards,
+            distances,
+            food_race,
+            chokepoints: OnceCell::new(),
+            kills: OnceCell::new(),
+        }
+    }
+
+    pub fn get_chokepoints(&self) -> &Chokepoints {
+        self.chokepoints
+            .get_or_init(|| Chokepoints::new(self.board))
+    }
+
+    /// Kill moves of snake `index`
+    pub fn get_kill_moves(&self, index: usize) -> &[KillMove] {
+        &self.kills.get_or_init(|| {
+            (0..self.board.snakes.len())
+                .map(|i| {
+                    self.board
+                        .get_kill_moves(&self.distances, self.get_chokepoints(), i)
+                })
+                .collect()
+        })[index]
+    }
+
+    /// Kill moves of snake `index` if they were needed already
+    pub fn get_found_kill_moves(&self, index: usize) -> Option<&[KillMove]> {
+        self.kills.get().map(|kills| kills[index].as_slice())
+    }
+}
+
+/// Snake's length relative to all snakes together
+pub struct Length;
+
+impl Component for Length {
+    fn name(&self) -> &'static str {
+        "length"
+    }
+
+    fn score(&self, position: &Position, index: usize) -> f32 {
+        let board = position.board;
+        let total: usize = board.snakes.iter().map(|s| s.size()).sum();
+        board.snakes[index].size() as f32 / total as f32
+    }
+}
+
+/// Health left after reaching the cheapest food, 1.0 if there is no food to care about
+pub struct FoodReach;
+
+impl Component for FoodReach {
+    fn name(&self) -> &'static str {
+        "food"
+    }
+
+    fn score(&self, position: &Position, index: usize) -> f32 {
+        let board = position.board;
+        if board.food.is_empty() {
+            return 1.0;
+        }
+        let hp = board.snakes[index].health as usize;
+        position
+            .food_race
+            .contests
+            .iter()
+            .filter_map(|contest| contest.arrivals[index])
+            .map(|arrival| (hp - arrival.cheapest_cost) as f32 / hp as f32)
+            .fold(0.0, f32::max)
+    }
+}
+
+/// Share of food the snake gets to first, ties counting half,
+/// 1.0 if there is no food to race for
+pub struct FoodContestShare;
+
+impl Component for FoodContestShare {
+    fn name(&self) -> &'static str {
+        "race"
+    }
+
+    fn score(&self, position: &Position, index: usize) -> f32 {
+        let races = position.food_race.get_races(index);
+        if races.is_empty() {
+            return 1.0;
+        }
+        let won: f32 = races
+            .iter()
+            .map(|(_, race)| match race {
+                Race::Won => 1.0,
+                Race::Tied => 0.5,
+                _ => 0.0,
+            })
+            .sum();
+        won / races.len() as f32
+    }
+}
+
+/// Reachable area, full score once the whole body fits in
+pub struct Area;
+
+impl Component for Area {
+    fn name(&self) -> &'static str {
+        "area"
+    }
+
+    fn score(&self, position: &Position, index: usize) -> f32 {
+        let area = position.distances.get_reachable(index);
+        (area as f32 / position.board.snakes[index].size() as f32).min(1.0)
+    }
+}
+
+/// Voronoi territory share, with a bit extra for food inside the territory
+pub struct TerritoryShare;
+
+impl Component for TerritoryShare {
+    fn name(&self) -> &'static str {
+        "territory"
+    }
+
+    fn score(&self, position: &Position, index: usize) -> f32 {
+        let territory = Territory::from_distances(position.board, &position.distances);
+        let total_food: usize = territory.food.iter().sum();
+        if total_food == 0 {
+            return territory.get_share(index);
+        }
+        0.8 * territory.get_share(index) + 0.2 * territory.food[index] as f32 / total_food as f32
+    }
+}
+
+/// Closeness to heads of smaller snakes, in steps around bodies
+pub struct Aggression {
+    /// score when there is no smaller snake
+    pub base: f32,
+    /// score when sharing the cell with a smaller snake's head
+    pub contact: f32,
+}
+
+impl Default for Aggression {
+    fn default() -> Self {
+        Self {
+            base: 0.0,
+            contact: 1.0,
+        }
+    }
+}
+
+impl Component for Aggression {
+    fn name(&self) -> &'static str {
+        "aggression"
+    }
+
+    fn score(&self, position: &Position, index: usize) -> f32 {
+        let board = position.board;
+        let me = &board.snakes[index];
+        board
+            .snakes
+            .iter()
+            .enumerate()
+            .filter(|(i, snake)| *i != index && snake.size() < me.size())
+            .filter_map(|(_, snake)| {
+                if snake.head() == me.head() {
+                    return Some(self.contact);
+                }
+                // the head itself is a body cell, get next to it
+                let steps = snake
+                    .head()
+                    .get_neighbours()
+                    .iter()
+                    .filter_map(|n| position.distances.get_steps(index, n))
+                    .min()?;
+                Some(1.0 / (steps + 1) as f32)
+            })
+            .fold(self.base, f32::max)
+    }
+}
+
+/// Penalty for standing in hazard, worse with low health
+pub struct HazardExposure {
+    /// health lost per turn in hazard
+    pub damage: f32,
+}
+
+impl Default for HazardExposure {
+    fn default() -> Self {
+        Self {
+            damage: HAZARD_DAMAGE,
+        }
+    }
+}
+
+impl Component for HazardExposure {
+    fn name(&self) -> &'static str {
+        "hazard"
+    }
+
+    fn score(&self, position: &Position, index: usize) -> f32 {
+        let snake = &position.board.snakes[index];
+        if !position.hazards.contains(&snake.head()) {
+            return 1.0;
+        }
+        ((snake.health as f32 - self.damage) / 100.0).max(0.0) * 0.5
+    }
+}
+
+/// Penalty for being in a pocket another snake can close, too small to fit
+/// the body, and reward for the snakes we can close in
+pub struct ChokepointControl;
+
+impl Component for ChokepointControl {
+    fn name(&self) -> &'static str {
+        "chokepoint"
+    }
+
+    fn score(&self, position: &Position, index: usize) -> f32 {
+        let board = position.board;
+        let chokepoints = position.get_chokepoints();
+        if let Some(pocket) = chokepoints.get_threat(&position.distances, index) {
+            let size = board.snakes[index].size() as f32;
+            return 0.5 * (pocket.inside as f32 / size).min(1.0);
+        }
+        let others = board.snakes.len().saturating_sub(1).max(1);
+        let cut_off = chokepoints.get_cut_off(&position.distances, index).len();
+        0.5 + 0.5 * cut_off as f32 / others as f32
+    }
+}
+
+/// Kill moves the snake has against smaller snakes, none if another snake
+/// has one against it
+pub struct KillThreat;
+
+impl Component for KillThreat {
+    fn name(&self) -> &'static str {
+        "tactics"
+    }
+
+    fn score(&self, position: &Position, index: usize) -> f32 {
+        let board = position.board;
+        let threatened = (0..board.snakes.len())
+            .filter(|i| *i != index)
+            .flat_map(|i| position.get_kill_moves(i))
+            .any(|kill| kill.target == index);
+        if threatened {
+            return 0.0;
+        }
+        let mut targets: Vec<usize> = position
+            .get_kill_moves(index)
+            .iter()
+            .map(|kill| kill.target)
+            .collect();
+        targets.sort_unstable();
+        targets.dedup();
+        let others = board.snakes.len().saturating_sub(1).max(1);
+        0.5 + 0.5 * targets.len() as f32 / others as f32
+    }
+}
+
+/// Evaluator weights and component constants, as read from the config file.
+/// Only components listed in `weights` are used.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EvaluatorConfig {
+    pub weights: BTreeMap<String, f32>,
+    pub aggression_base: f32,
+    pub aggression_contact: f32,
+    pub hazard_damage: f32,
+    pub hazard_cost: usize,
+}
+
+impl Default for EvaluatorConfig {
+    fn default() -> Self {
+        Self {
+            weights: DEFAULT_WEIGHTS
+                .iter()
+                .map(|(name, w)| (name.to_string(), *w))
+                .collect(),
+            aggression_base: 0.0,
+            aggression_contact: 1.0,
+            hazard_damage: HAZARD_DAMAGE,
+            hazard_cost: HAZARD_COST,
+        }
+    }
+}
+
+impl EvaluatorConfig {
+    /// Returns component by its name
+    pub fn get_component(&self, name: &str) -> Option<Box<dyn Component>> {
+        match name {
+            "length" => Some(Box::new(Length)),
+            "food" => Some(Box::new(FoodReach)),
+            "race" => Some(Box::new(FoodContestShare)),
+            "area" => Some(Box::new(Area)),
+            "territory" => Some(Box::new(TerritoryShare)),
+            "aggression" => Some(Box::new(Aggression {
+                base: self.aggression_base,
+                contact: self.aggression_contact,
+            })),
+            "hazard" => Some(Box::new(HazardExposure {
+                damage: self.hazard_damage,
+            })),
+            "chokepoint" => Some(Box::new(ChokepointControl)),
+            "tactics" => Some(Box::new(KillThreat)),
+            _ => None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, weight) in self.weights.iter() {
+            if self.get_component(name).is_none() {
+                return Err(format!("Unknown evaluator component \"{}\"", name));
+            }
+            if !weight.is_finite() || *weight < 0.0 {
+                return Err(format!("Invalid weight {} of \"{}\"", weight, name));
+            }
+        }
+        if self.weights.values().sum::<f32>() <= 0.0 {
+            return Err("At least one evaluator weight has to be positive".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.aggression_base)
+            || !(0.0..=1.0).contains(&self.aggression_contact)
+        {
+            return Err("Aggression scores have to be within 0.0 - 1.0".to_string());
+        }
+        if !(0.0..=100.0).contains(&self.hazard_damage) {
+            return Err(format!("Invalid hazard damage {}", self.hazard_damage));
+        }
+        if self.hazard_cost == 0 {
+            return Err("Hazard cost has to be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn build(&self) -> WeightedEvaluator {
+        self.weights
+            .iter()
+            .filter_map(|(name, w)| self.get_component(name).map(|c| (*w, c)))
+            .fold(
+                WeightedEvaluator::new().with_hazard_cost(self.hazard_cost),
+                |e, (w, c)| e.with(w, c),
+            )
+    }
+}
+
+/// Weighted average of components
+pub struct WeightedEvaluator {
+    components: Vec<(f32, Box<dyn Component>)>,
+    hazard_cost: usize,
+}
+
+impl WeightedEvaluator {
+    pub fn new() -> Self {
+        Self {
+            components: Vec::new(),
+            hazard_cost: HAZARD_COST,
+        }
+    }
+
+    /// Path cost of a hazard tile in the distance maps
+    pub fn with_hazard_cost(mut self, hazard_cost: usize) -> Self {
+        self.hazard_cost = hazard_cost;
+        self
+    }
+
+    pub fn with(mut self, weight: f32, component: Box<dyn Component>) -> Self {
+        self.components.push((weight, component));
+        self
+    }
+
+    pub fn get_weights(&self) -> Vec<(&'static str, f32)> {
+        self.components
+            .iter()
+            .map(|(w, c)| (c.name(), *w))
+            .collect()
+    }
+}
+
+impl Default for WeightedEvaluator {
+    fn default() -> Self {
+        EvaluatorConfig::default().build()
+    }
+}
+
+impl Evaluator for WeightedEvaluator {
+    fn evaluate(&self, board: &Board, index: usize, hazards: &[Point]) -> f32 {
+        self.evaluate_with_kills(board, index, hazards).0
+    }
+
+    fn evaluate_with_kills(
+        &self,
+        board: &Board,
+        index: usize,
+        hazards: &[Point],
+    ) -> (f32, Option<Vec<Direction>>) {
+        if index == 0 && board.snakes.len() == 1 {
+            return (WIN_SCORE, None);
+        }
+        let total_weight: f32 = self.components.iter().map(|(w, _)| w).sum();
+        if total_weight <= 0.0 {
+            return (0.0, None);
+        }
+        let position = Position::new(board, hazards, self.hazard_cost);
+        let score = self
+            .components
+            .iter()
+            .filter(|(w, _)| *w > 0.0)
+            .map(|(w, c)| w * c.score(&position, index))
+            .sum::<f32>()
+            / total_weight;
+        let kills = position
+            .get_found_kill_moves(index)
+            .map(|kills| kills.iter().map(|kill| kill.dir).collect());
+        (score, kills)
+    }
+}
+
+impl fmt::Display for WeightedEvaluator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let weights: Vec<String> = self
+            .get_weights()
+            .iter()
+            .map(|(name, w)| format!("{}={}", name, w))
+            .collect();
+        write!(f, "{}", weights.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::GameInfo;
+    use super::*;
+
+    fn board() -> (Board, Vec<Point>) {
+        let gameinfo = GameInfo::new(
+            r#"{
+            "game": {"id": "game-1", "timeout": 500},
+            "turn": 5,
+            "board": {
+                "height": 11,
+                "width": 11,
+                "food": [{"x": 9, "y": 5}],
+                "hazards": [{"x": 9, "y": 1}, {"x": 8, "y": 1}, {"x": 10, "y": 1}],
+                "snakes": [
+                    {
+                        "id": "me", "name": "Go  Giddy", "health": 50,
+                        "body": [{"x": 9, "y": 1}, {"x": 9, "y": 2}, {"x": 9, "y": 3}, {"x": 9, "y": 4}],
+                        "head": {"x": 9, "y": 1}, "length": 4, "shout": ""
+                    },
+                    {
+                        "id": "enemy", "name": "Other", "health": 90,
+                        "body": [{"x": 5, "y": 1}, {"x": 5, "y": 2}, {"x": 5, "y": 3}],
+                        "head": {"x": 5, "y": 1}, "length": 3, "shout": ""
+                    }
+                ]
+            },
+            "you": {
+                "id": "me", "name": "Go  Giddy", "health": 50,
+                "body": [{"x": 9, "y": 1}, {"x": 9, "y": 2}, {"x": 9, "y": 3}, {"x": 9, "y": 4}],
+                "head": {"x": 9, "y": 1}, "length": 4, "shout": ""
+            }
+        }"#,
+        );
+        (Board::from_api(&gameinfo), gameinfo.get_hazards().clone())
+    }
+
+    #[test]
+    fn components() {
+        let (board, hazards) = board();
+        let position = Position::new(&board, &hazards, HAZARD_COST);
+        assert!((Length.score(&position, 0) - 4.0 / 7.0).abs() < 1e-5);
+        let aggression = Aggression::default();
+        assert_eq!(aggression.score(&position, 0), 0.25);
+        assert_eq!(aggression.score(&position, 1), 0.0);
+        assert_eq!(Area.score(&position, 0), 1.0);
+        let hazard = HazardExposure::default();
+        assert_eq!(hazard.score(&position, 1), 1.0);
+        assert!((hazard.score(&position, 0) - 0.175).abs() < 1e-5);
+        let food = FoodReach.score(&position, 0);
+        // going around the hazards costs 10, the fastest way through them 20
+        assert!((food - 40.0 / 50.0).abs() < 1e-5);
+        // same as the cheapest path found by A*
+        let (cost, _) = board
+            .astar_with_cost(Point::new(9, 1), Point::new(9, 5), &hazards, HAZARD_COST)
+            .unwrap();
+        assert_eq!(cost, 10);
+        // the way around stays the cheapest however expensive the hazard
+        let expensive = Position::new(&board, &hazards, 30);
+        assert_eq!(FoodReach.score(&expensive, 0), food);
+        // we are closer to the only food
+        assert_eq!(FoodContestShare.score(&position, 0), 1.0);
+        assert_eq!(FoodContestShare.score(&position, 1), 0.0);
+        // nobody is boxed in
+        assert_eq!(ChokepointControl.score(&position, 0), 0.5);
+        assert_eq!(ChokepointControl.score(&position, 1), 0.5);
+        // too far apart for a kill
+        assert_eq!(KillThreat.score(&position, 0), 0.5);
+        assert_eq!(KillThreat.score(&position, 1), 0.5);
+    }
+
+    #[test]
+    fn weighted() {
+        let (board, hazards) = board();
+        let length_only = WeightedEvaluator::new().with(2.0, Box::new(Length));
+        assert!((length_only.evaluate(&board, 0, &hazards) - 4.0 / 7.0).abs() < 1e-5);
+        let mixed = WeightedEvaluator::new()
+            .with(1.0, Box::new(Length))
+            .with(1.0, Box::new(Area))
+            .with(0.0, Box::new(Aggression::default()));
+        let expected = (4.0 / 7.0 + 1.0) / 2.0;
+        assert!((mixed.evaluate(&board, 0, &hazards) - expected).abs() < 1e-5);
+        assert_eq!(format!("{}", mixed), "length=1, area=1, aggression=0");
+        let default = WeightedEvaluator::default();
+        assert_eq!(default.get_weights().len(), 7);
+        let score = default.evaluate(&board, 0, &hazards);
+        assert!(score > 0.0 && score < 1.0);
+    }
+
+    #[test]
+    fn config() {
+        let mut config: EvaluatorConfig = serde_json::from_str(
+            r#"{"weights": {"length": 1.0, "aggression": 1.0}, "aggression_base": 0.1}"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.hazard_cost, HAZARD_COST);
+        assert_eq!(format!("{}", config.build()), "aggression=1, length=1");
+        let (board, hazards) = board();
+        // no smaller snake for the enemy, gets the base aggression
+        let expected = (3.0 / 7.0 + 0.1) / 2.0;
+        assert!((config.build().evaluate(&board, 1, &hazards) - expected).abs() < 1e-5);
+        config.weights.insert("cowardice".to_string(), 1.0);
+        assert!(config.validate().is_err());
+        config.weights.clear();
+        assert!(config.validate().is_err());
+        assert!(serde_json::from_str::<EvaluatorConfig>(r#"{"wieghts": {}}"#).is_err());
+        assert!(EvaluatorConfig::default().validate().is_ok());
+    }
+}
@@ -0,0 +1,121 @@
+//! Flood fill of the board that accounts for receding tails
+//!
+//! A body segment blocks its cell only until the segment moves away, so a cell
+//! occupied by the n-th segment from the tail becomes free after n turns.
+//! Stacked tail segments (snake has just eaten) naturally keep the cell one turn longer.
+//!
+use std::collections::VecDeque;
+
+use super::Board;
+
+impl Board {
+    /// Turn at which each cell becomes free, 0 for empty cells
+    pub fn get_vacate_times(&self) -> Vec<usize> {
+        let mut times = vec![0; self.get_width() * self.get_height()];
+        for snake in self.snakes.iter() {
+            let size = snake.size();
+            // walking from the tail keeps the lowest index for stacked segments
+            for (i, p) in snake.body.nodes.iter().enumerate().rev() {
+                if self.is_inbounds(p) {
+                    times[self.get_index(p)] = size - i;
+                }
+            }
+        }
+        times
+    }
+
+    /// Number of cells snake `index` can reach, including cells that get vacated
+    /// by the time the snake gets there. Other snakes are assumed to stay on their paths.
+    pub fn flood_fill(&self, index: usize) -> usize {
+        let times = self.get_vacate_times();
+        let head = self.snakes[index].head();
+        if !self.is_inbounds(&head) {
+            return 0;
+        }
+        let mut visited = vec![false; times.len()];
+        let mut queue: VecDeque<(usize, usize)> = VecDeque::new();
+        let start = self.get_index(&head);
+        visited[start] = true;
+        queue.push_back((start, 0));
+        let mut area = 0;
+        while let Some((i, turn)) = queue.pop_front() {
+            for n in self.get_point(i).get_neighbours() {
+                if !self.is_inbounds(&n) {
+                    continue;
+                }
+                let j = self.get_index(&n);
+                if !visited[j] && times[j] <= turn + 1 {
+                    visited[j] = true;
+                    area += 1;
+                    queue.push_back((j, turn + 1));
+                }
+            }
+        }
+        area
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{GameInfo, Point};
+    use super::*;
+
+    fn two_snakes(body: &str, other: &str) -> Board {
+        Board::from_api(&GameInfo::new(&format!(
+            r#"{{
+            "game": {{"id": "game-1", "timeout": 500}},
+            "turn": 5,
+            "board": {{
+                "height": 5,
+                "width": 5,
+                "food": [],
+                "hazards": [],
+                "snakes": [
+                    {{"id": "me", "name": "me", "health": 90, "body": [{}], "head": {{"x": 0, "y": 0}}, "length": 0, "shout": ""}},
+                    {{"id": "other", "name": "other", "health": 90, "body": [{}], "head": {{"x": 0, "y": 0}}, "length": 0, "shout": ""}}
+                ]
+            }},
+            "you": {{"id": "me", "name": "me", "health": 90, "body": [{}], "head": {{"x": 0, "y": 0}}, "length": 0, "shout": ""}}
+        }}"#,
+            body, other, body
+        )))
+    }
+
+    #[test]
+    fn vacate_times() {
+        let board = two_snakes(
+            r#"{"x": 0, "y": 0}, {"x": 1, "y": 0}, {"x": 2, "y": 0}, {"x": 2, "y": 0}"#,
+            r#"{"x": 4, "y": 4}, {"x": 4, "y": 3}"#,
+        );
+        let times = board.get_vacate_times();
+        assert_eq!(times[board.get_index(&Point::new(0, 0))], 4);
+        assert_eq!(times[board.get_index(&Point::new(1, 0))], 3);
+        // stacked tail stays one turn longer
+        assert_eq!(times[board.get_index(&Point::new(2, 0))], 2);
+        assert_eq!(times[board.get_index(&Point::new(4, 3))], 1);
+        assert_eq!(times[board.get_index(&Point::new(3, 3))], 0);
+    }
+
+    #[test]
+    fn receding_wall() {
+        // the other snake walls off the left column, we are sealed in {0, 0} - {0, 4}
+        let board = two_snakes(
+            r#"{"x": 0, "y": 2}, {"x": 0, "y": 3}"#,
+            r#"{"x": 1, "y": 4}, {"x": 1, "y": 3}, {"x": 1, "y": 2}, {"x": 1, "y": 1}, {"x": 1, "y": 0}"#,
+        );
+        // {1, 0} frees after 1 turn, {1, 1} after 2 etc. so the whole board is reachable
+        assert_eq!(board.flood_fill(0), 24);
+        let board = two_snakes(
+            r#"{"x": 0, "y": 2}, {"x": 0, "y": 3}"#,
+            r#"{"x": 1, "y": 0}, {"x": 1, "y": 1}, {"x": 1, "y": 2}, {"x": 1, "y": 3}, {"x": 1, "y": 4}"#,
+        );
+        // now the wall recedes from the top, reached only after it's gone
+        assert_eq!(board.flood_fill(0), 24);
+        let board = two_snakes(
+            r#"{"x": 0, "y": 2}, {"x": 0, "y": 3}"#,
+            r#"{"x": 1, "y": 0}, {"x": 1, "y": 1}, {"x": 1, "y": 2}, {"x": 1, "y": 3}, {"x": 1, "y": 4}, {"x": 2, "y": 4}, {"x": 3, "y": 4}, {"x": 4, "y": 4}, {"x": 4, "y": 3}"#,
+        );
+        // wall stays long enough: only {0, 0}, {0, 1}, {0, 4} and own tail {0, 3}
+        assert_eq!(board.flood_fill(0), 4);
+    }
+}
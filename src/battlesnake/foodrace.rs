@@ -0,0 +1,243 @@
+//! Food contest analysis
+//!
+//! For every food, the turn each snake can get there and the health it costs on
+//! the way, both of the fastest way in the distance maps or, if the snake can't
+//! afford it, of the cheapest way. Snakes without the health to make it don't
+//! take part. The food goes to the snake arriving first; snakes
+//! arriving on the same turn meet head to head, so the longest of them wins and
+//! equally long ones tie, dying together.
+//!
+use super::distance::DistanceMaps;
+use super::Board;
+use super::Point;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arrival {
+    /// turns until the head is on the food
+    pub turn: usize,
+    /// health lost on the way, eating on a hazard tile doesn't hurt
+    pub health_cost: usize,
+    /// health lost on the cheapest way, which can take longer
+    pub cheapest_cost: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Race {
+    Won,
+    Tied,
+    Lost,
+    Unreachable,
+}
+
+#[derive(Debug, Clone)]
+pub struct FoodContest {
+    pub food: Point,
+    /// arrival of each snake, none if it can't make it
+    pub arrivals: Vec<Option<Arrival>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FoodRace {
+    pub contests: Vec<FoodContest>,
+    sizes: Vec<usize>,
+}
+
+impl FoodRace {
+    pub fn new(
+        board: &Board,
+        distances: &DistanceMaps,
+        hazards: &[Point],
+        hazard_cost: usize,
+    ) -> Self {
+        let contests = board
+            .food
+            .iter()
+            .map(|food| {
+                let arrivals = board
+                    .snakes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, snake)| {
+                        let health_cost = |cost: usize| {
+                            if hazards.contains(food) {
+                                cost.saturating_sub(hazard_cost - 1)
+                            } else {
+                                cost
+                            }
+                        };
+                        let (cost, steps) = distances.get_cheapest(i, food)?;
+                        let cheapest_cost = health_cost(cost);
+                        if cheapest_cost > snake.health as usize {
+                            return None;
+                        }
+                        let (cost, turn) = distances
+                            .get_fastest(i, food)
+                            .filter(|(cost, _)| health_cost(*cost) <= snake.health as usize)
+                            .unwrap_or((cost, steps));
+                        Some(Arrival {
+                            turn,
+                            health_cost: health_cost(cost),
+                            cheapest_cost,
+                        })
+                    })
+                    .collect();
+                FoodContest {
+                    food: *food,
+                    arrivals,
+                }
+            })
+            .collect();
+        Self {
+            contests,
+            sizes: board.snakes.iter().map(|s| s.size()).collect(),
+        }
+    }
+
+    /// Outcome of the race for `contest` from the view of snake `index`
+    pub fn get_race(&self, contest: &FoodContest, index: usize) -> Race {
+        let mine = match contest.arrivals[index] {
+            Some(arrival) => arrival,
+            None => return Race::Unreachable,
+        };
+        let mut race = Race::Won;
+        for (i, other) in contest.arrivals.iter().enumerate() {
+            let other = match other {
+                Some(other) if i != index => other,
+                _ => continue,
+            };
+            if other.turn < mine.turn
+                || (other.turn == mine.turn && self.sizes[i] > self.sizes[index])
+            {
+                return Race::Lost;
+            }
+            if other.turn == mine.turn && self.sizes[i] == self.sizes[index] {
+                race = Race::Tied;
+            }
+        }
+        race
+    }
+
+    /// Food and the race outcome for snake `index`
+    pub fn get_races(&self, index: usize) -> Vec<(Point, Race)> {
+        self.contests
+            .iter()
+            .map(|contest| (contest.food, self.get_race(contest, index)))
+            .collect()
+    }
+
+    /// Food snake `index` gets to first without risking a head to head
+    pub fn get_targets(&self, index: usize) -> Vec<Point> {
+        self.get_races(index)
+            .into_iter()
+            .filter(|(_, race)| *race == Race::Won)
+            .map(|(food, _)| food)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::GameInfo;
+    use super::*;
+
+    #[test]
+    fn races() {
+        let board = Board::from_api(&GameInfo::new(
+            r#"{
+            "game": {"id": "game-1", "timeout": 500},
+            "turn": 5,
+            "board": {
+                "height": 7,
+                "width": 7,
+                "food": [{"x": 0, "y": 3}, {"x": 6, "y": 3}, {"x": 3, "y": 6}, {"x": 3, "y": 0}, {"x": 2, "y": 5}],
+                "hazards": [],
+                "snakes": [
+                    {"id": "me", "name": "me", "health": 90, "body": [{"x": 1, "y": 3}, {"x": 1, "y": 2}, {"x": 1, "y": 1}], "head": {"x": 1, "y": 3}, "length": 3, "shout": ""},
+                    {"id": "other", "name": "other", "health": 90, "body": [{"x": 5, "y": 3}, {"x": 5, "y": 2}, {"x": 5, "y": 1}, {"x": 5, "y": 0}], "head": {"x": 5, "y": 3}, "length": 4, "shout": ""},
+                    {"id": "third", "name": "third", "health": 3, "body": [{"x": 3, "y": 3}, {"x": 3, "y": 4}, {"x": 3, "y": 5}], "head": {"x": 3, "y": 3}, "length": 3, "shout": ""}
+                ]
+            },
+            "you": {"id": "me", "name": "me", "health": 90, "body": [{"x": 1, "y": 3}, {"x": 1, "y": 2}, {"x": 1, "y": 1}], "head": {"x": 1, "y": 3}, "length": 3, "shout": ""}
+        }"#,
+        ));
+        let hazards = vec![Point::new(3, 0)];
+        let distances = board.get_distance_maps(&hazards, 10);
+        let race = FoodRace::new(&board, &distances, &hazards, 10);
+        let races = race.get_races(0);
+        assert_eq!(races[0], (Point::new(0, 3), Race::Won));
+        assert_eq!(races[1], (Point::new(6, 3), Race::Lost));
+        // head to head with the longer snake at the top
+        assert_eq!(race.contests[2].arrivals[0].unwrap().turn, 5);
+        assert_eq!(races[2].1, Race::Lost);
+        assert_eq!(race.get_race(&race.contests[2], 1), Race::Won);
+        // the third snake starves on the way
+        assert_eq!(race.contests[2].arrivals[2], None);
+        // the hazard on the food itself doesn't count, so it makes it here
+        assert_eq!(
+            race.contests[3].arrivals[0],
+            Some(Arrival {
+                turn: 5,
+                health_cost: 5,
+                cheapest_cost: 5
+            })
+        );
+        assert_eq!(
+            race.contests[3].arrivals[2],
+            Some(Arrival {
+                turn: 3,
+                health_cost: 3,
+                cheapest_cost: 3
+            })
+        );
+        assert_eq!(races[3].1, Race::Lost);
+        assert_eq!(races[4], (Point::new(2, 5), Race::Tied));
+        assert_eq!(race.get_targets(0), vec![Point::new(0, 3)]);
+        assert_eq!(race.get_race(&race.contests[0], 2), Race::Unreachable);
+    }
+
+    #[test]
+    fn hazard_detour() {
+        // a row of hazards between us and the food
+        let race = |health: usize| {
+            let board = Board::from_api(&GameInfo::new(&format!(
+                r#"{{
+                "game": {{"id": "game-1", "timeout": 500}},
+                "turn": 5,
+                "board": {{
+                    "height": 5,
+                    "width": 5,
+                    "food": [{{"x": 4, "y": 2}}],
+                    "hazards": [],
+                    "snakes": [
+                        {{"id": "me", "name": "me", "health": {}, "body": [{{"x": 0, "y": 2}}, {{"x": 0, "y": 1}}, {{"x": 0, "y": 0}}], "head": {{"x": 0, "y": 2}}, "length": 3, "shout": ""}}
+                    ]
+                }},
+                "you": {{"id": "me", "name": "me", "health": {}, "body": [{{"x": 0, "y": 2}}, {{"x": 0, "y": 1}}, {{"x": 0, "y": 0}}], "head": {{"x": 0, "y": 2}}, "length": 3, "shout": ""}}
+            }}"#,
+                health, health
+            )));
+            let hazards = vec![Point::new(1, 2), Point::new(2, 2), Point::new(3, 2)];
+            let distances = board.get_distance_maps(&hazards, 10);
+            FoodRace::new(&board, &distances, &hazards, 10).contests[0].arrivals[0]
+        };
+        // straight through the hazards when there is the health for it
+        assert_eq!(
+            race(90),
+            Some(Arrival {
+                turn: 4,
+                health_cost: 31,
+                cheapest_cost: 6
+            })
+        );
+        // around them otherwise, turn and cost of the same way
+        assert_eq!(
+            race(20),
+            Some(Arrival {
+                turn: 6,
+                health_cost: 6,
+                cheapest_cost: 6
+            })
+        );
+        assert_eq!(race(5), None);
+    }
+}
@@ -1,12 +1,383 @@
-//! Game state for minmax, ab pruning and iterative deepening eventualy - Possibly with EG for game state evaluation? Or even MCTS?
-//! - for now consider using HEALTH as move indicator
-//! - the gamestate should be evaluate self somehow
-//! 
-use super::GameGrid;
-
-pub struct GameState {
-    grid: GameGrid,
-    me: Option<Snake>,
-    oponents: Option<Vec<Snake>>,
-    remaining: Time,
-}
\ No newline at end of file
+//! Per-game persistent state shared between `/start`, `/move` and `/end`
+//!
+//! Every `/move` request carries the full board, but nothing about the previous
+//! turns. The `GameRegistry` keeps a `GameContext` for each running game (keyed
+//! by `game.id`) so strategies can remember what opponents did and what we
+//! planned last turn. Each game also keeps the config that was active at its
+//! start, so reloading the config never changes the strategy mid-game.
+//!
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use super::config::Config;
+use super::opponent::{BehaviourProfile, OpponentModel};
+use super::Board;
+use super::Direction;
+use super::GameInfo;
+use super::Point;
+
+/// Moves observed for a single opponent during the game
+#[derive(Debug, Clone)]
+pub struct OpponentHistory {
+    pub name: String,
+    /// (turn, direction) pairs in the order they were observed
+    pub moves: Vec<(i32, Direction)>,
+    pub last_head: Point,
+    pub last_length: i32,
+    pub last_health: i32,
+    /// Behaviour learned in this game
+    pub profile: BehaviourProfile,
+    /// Behaviour learned from previous games against snake of the same name
+    pub prior: BehaviourProfile,
+}
+
+impl OpponentHistory {
+    fn new(name: &str, head: Point, length: i32, health: i32) -> Self {
+        Self {
+            name: name.to_string(),
+            moves: Vec::new(),
+            last_head: head,
+            last_length: length,
+            last_health: health,
+            profile: BehaviourProfile::new(),
+            prior: BehaviourProfile::new(),
+        }
+    }
+}
+
+/// Summary of the last search run for this game
+#[derive(Debug, Clone, Default)]
+pub struct SearchMemory {
+    pub turn: i32,
+    /// Best score found for each of our root moves, indexed by `Direction::as_index`
+    pub root_scores: [Option<f32>; 4],
+    pub depth: usize,
+    pub explored: usize,
+}
+
+impl fmt::Display for SearchMemory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let scores: Vec<String> = self
+            .root_scores
+            .iter()
+            .map(|s| s.map_or("-".to_string(), |v| format!("{:.3}", v)))
+            .collect();
+        write!(
+            f,
+            "Turn {} searched to depth {} ({} explored), root scores [{}]",
+            self.turn,
+            self.depth,
+            self.explored,
+            scores.join(", ")
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct GameContext {
+    pub id: String,
+    pub my_id: String,
+    pub turn: i32,
+    started: SystemTime,
+    last_seen: SystemTime,
+    /// Opponent move histories keyed by snake id
+    pub opponents: HashMap<String, OpponentHistory>,
+    /// Our intended line of play from the previous turn, first element is the move we sent
+    pub plan: Vec<Direction>,
+    pub search: Option<SearchMemory>,
+    /// Config snapshot taken when the game started
+    pub config: Arc<Config>,
+    /// Board of the last observed turn and snake ids in its order
+    last_board: Option<Board>,
+    board_ids: Vec<String>,
+}
+
+impl GameContext {
+    pub fn new(gameinfo: &GameInfo, config: Arc<Config>) -> Self {
+        let now = SystemTime::now();
+        let mut ctx = Self {
+            id: gameinfo.get_game_id(),
+            my_id: gameinfo.get_my_id().to_string(),
+            turn: gameinfo.get_turn(),
+            started: now,
+            last_seen: now,
+            opponents: HashMap::new(),
+            plan: Vec::new(),
+            search: None,
+            config,
+            last_board: None,
+            board_ids: Vec::new(),
+        };
+        ctx.observe(gameinfo);
+        ctx
+    }
+
+    /// Updates opponent histories from a new turn.
+    /// Moves are inferred from head displacement, so repeated turns or gaps are skipped.
+    pub fn observe(&mut self, gameinfo: &GameInfo) {
+        let turn = gameinfo.get_turn();
+        self.last_seen = SystemTime::now();
+        for snake in gameinfo.board.snakes.iter() {
+            if snake.id == self.my_id {
+                continue;
+            }
+            let history = self.opponents.entry(snake.id.clone()).or_insert_with(|| {
+                OpponentHistory::new(&snake.name, snake.head, snake.length, snake.health)
+            });
+            if turn > self.turn {
+                if let Ok(dir) = Direction::try_from(snake.head - history.last_head) {
+                    history.moves.push((turn - 1, dir));
+                    let last_index = self.board_ids.iter().position(|id| *id == snake.id);
+                    if let (Some(board), Some(index)) = (self.last_board.as_ref(), last_index) {
+                        history.profile.observe(board, index, dir);
+                    }
+                }
+            }
+            history.last_head = snake.head;
+            history.last_length = snake.length;
+            history.last_health = snake.health;
+        }
+        self.turn = turn;
+        self.last_board = Some(Board::from_api(gameinfo));
+        self.board_ids = get_board_ids(gameinfo);
+    }
+
+    /// Opponent model for the last observed board, combining this game with priors
+    pub fn opponent_model(&self) -> OpponentModel {
+        OpponentModel::new(
+            self.board_ids
+                .iter()
+                .map(|id| {
+                    self.opponents.get(id).map(|history| {
+                        let mut profile = history.prior.clone();
+                        profile.merge(&history.profile);
+                        profile
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    /// Stores the line we are about to play and what the search found
+    pub fn set_plan(&mut self, plan: Vec<Direction>, search: SearchMemory) {
+        self.plan = plan;
+        self.search = Some(search);
+    }
+
+    /// Returns the move our previous plan expected us to play this turn
+    pub fn planned_move(&self) -> Option<Direction> {
+        self.plan.get(1).cloned()
+    }
+
+    /// Whether there was no request for the game within `timeout` before `now`
+    pub fn is_stale(&self, now: SystemTime, timeout: Duration) -> bool {
+        now.duration_since(self.last_seen)
+            .unwrap_or(Duration::from_millis(1))
+            > timeout
+    }
+
+    pub fn get_duration(&self) -> Duration {
+        self.last_seen
+            .duration_since(self.started)
+            .unwrap_or(Duration::from_millis(0))
+    }
+}
+
+/// Snake ids in the same order as `Board::from_api` places them
+fn get_board_ids(gameinfo: &GameInfo) -> Vec<String> {
+    let mut ids = vec![gameinfo.you.id.clone()];
+    for snake in gameinfo.board.snakes.iter() {
+        if snake.id != gameinfo.you.id {
+            ids.push(snake.id.clone());
+        }
+    }
+    ids
+}
+
+/// Registry of running games
+pub struct GameRegistry {
+    games: HashMap<String, Arc<Mutex<GameContext>>>,
+    /// Behaviour profiles learned over finished games, keyed by snake name
+    profiles: HashMap<String, BehaviourProfile>,
+    timeout: Duration,
+}
+
+impl GameRegistry {
+    /// timeout in seconds since the last request for the game
+    pub fn new(timeout: u64) -> Self {
+        Self {
+            games: HashMap::new(),
+            profiles: HashMap::new(),
+            timeout: Duration::from_secs(timeout),
+        }
+    }
+
+    pub fn start_game(&mut self, gameinfo: &GameInfo, config: Arc<Config>) -> Arc<Mutex<GameContext>> {
+        let mut ctx = GameContext::new(gameinfo, config);
+        for history in ctx.opponents.values_mut() {
+            if let Some(prior) = self.profiles.get(&history.name) {
+                history.prior = prior.clone();
+            }
+        }
+        let ctx = Arc::new(Mutex::new(ctx));
+        self.games.insert(gameinfo.get_game_id(), ctx.clone());
+        ctx
+    }
+
+    /// Returns context of the game, creating it if `/start` was missed (e.g. after a restart)
+    pub fn get_game(&mut self, gameinfo: &GameInfo, config: Arc<Config>) -> Arc<Mutex<GameContext>> {
+        match self.games.get(&gameinfo.get_game_id()) {
+            Some(ctx) => ctx.clone(),
+            None => self.start_game(gameinfo, config),
+        }
+    }
+
+    /// Removes the game and keeps what was learned about the opponents
+    pub fn end_game(&mut self, id: &str) -> Option<Arc<Mutex<GameContext>>> {
+        let game = self.games.remove(id)?;
+        if let Ok(ctx) = game.lock() {
+            for history in ctx.opponents.values() {
+                self.profiles
+                    .entry(history.name.clone())
+                    .or_default()
+                    .merge(&history.profile);
+            }
+        }
+        Some(game)
+    }
+
+    /// Ends games that have not received a request within the timeout
+    pub fn garbage_collect(&mut self) {
+        self.garbage_collect_at(SystemTime::now());
+    }
+
+    /// Ends games that have not received a request within the timeout before `now`
+    fn garbage_collect_at(&mut self, now: SystemTime) {
+        let timeout = self.timeout;
+        let stale: Vec<String> = self
+            .games
+            .iter()
+            .filter(|(_, ctx)| ctx.lock().map(|c| c.is_stale(now, timeout)).unwrap_or(true))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in stale.iter() {
+            self.end_game(id);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+}
+
+impl fmt::Display for GameRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Game registry holds {} games, {} opponent profiles.",
+            self.len(),
+            self.profiles.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn game_at_turn(turn: i32, enemy_head: (i32, i32)) -> GameInfo {
+        GameInfo::new(&format!(
+            r#"{{
+            "game": {{"id": "game-1", "timeout": 500}},
+            "turn": {},
+            "board": {{
+                "height": 11,
+                "width": 11,
+                "food": [],
+                "hazards": [],
+                "snakes": [
+                    {{
+                        "id": "me", "name": "Go  Giddy", "health": 90,
+                        "body": [{{"x": 1, "y": 1}}, {{"x": 1, "y": 2}}, {{"x": 1, "y": 3}}],
+                        "head": {{"x": 1, "y": 1}}, "length": 3, "shout": ""
+                    }},
+                    {{
+                        "id": "enemy", "name": "Other", "health": 90,
+                        "body": [{{"x": {}, "y": {}}}, {{"x": 5, "y": 4}}, {{"x": 5, "y": 3}}],
+                        "head": {{"x": {}, "y": {}}}, "length": 3, "shout": ""
+                    }}
+                ]
+            }},
+            "you": {{
+                "id": "me", "name": "Go  Giddy", "health": 90,
+                "body": [{{"x": 1, "y": 1}}, {{"x": 1, "y": 2}}, {{"x": 1, "y": 3}}],
+                "head": {{"x": 1, "y": 1}}, "length": 3, "shout": ""
+            }}
+        }}"#,
+            turn, enemy_head.0, enemy_head.1, enemy_head.0, enemy_head.1
+        ))
+    }
+
+    #[test]
+    fn observe_opponents() {
+        let mut ctx = GameContext::new(&game_at_turn(0, (5, 5)), Arc::default());
+        ctx.observe(&game_at_turn(1, (5, 6)));
+        ctx.observe(&game_at_turn(2, (6, 6)));
+        // repeated turn is not recorded twice
+        ctx.observe(&game_at_turn(2, (6, 6)));
+        let enemy = ctx.opponents.get("enemy").unwrap();
+        assert_eq!(enemy.moves, vec![(0, Direction::Up), (1, Direction::Right)]);
+        assert!(!ctx.opponents.contains_key("me"));
+    }
+
+    #[test]
+    fn registry_lifecycle() {
+        let mut registry = GameRegistry::new(1);
+        let game = game_at_turn(0, (5, 5));
+        registry.start_game(&game, Arc::default());
+        assert_eq!(registry.len(), 1);
+        // running game keeps the config it started with
+        let reloaded = Arc::new(Config::from_json(r#"{"search": {"mode": "expectimax"}}"#).unwrap());
+        assert_eq!(*registry.get_game(&game, reloaded).lock().unwrap().config, Config::default());
+        let ctx = registry.get_game(&game, Arc::default());
+        ctx.lock().unwrap().set_plan(
+            vec![Direction::Up, Direction::Left],
+            SearchMemory::default(),
+        );
+        assert_eq!(
+            registry.get_game(&game, Arc::default()).lock().unwrap().planned_move(),
+            Some(Direction::Left)
+        );
+        assert!(registry.end_game("game-1").is_some());
+        assert_eq!(registry.len(), 0);
+        registry.get_game(&game, Arc::default());
+        registry.garbage_collect();
+        assert_eq!(registry.len(), 1);
+        registry.garbage_collect_at(SystemTime::now() + Duration::from_secs(2));
+        assert_eq!(registry.len(), 0);
+    }
+
+    #[test]
+    fn profiles_carry_over() {
+        let mut registry = GameRegistry::new(60);
+        let ctx = registry.start_game(&game_at_turn(0, (5, 5)), Arc::default());
+        ctx.lock().unwrap().observe(&game_at_turn(1, (5, 6)));
+        ctx.lock().unwrap().observe(&game_at_turn(2, (5, 7)));
+        assert_eq!(ctx.lock().unwrap().opponents["enemy"].profile.observed, 2);
+        registry.end_game("game-1");
+        let ctx = registry.start_game(&game_at_turn(0, (5, 5)), Arc::default());
+        let ctx = ctx.lock().unwrap();
+        assert_eq!(ctx.opponents["enemy"].prior.observed, 2);
+        assert_eq!(ctx.opponents["enemy"].profile.observed, 0);
+        assert!(
+            ctx.opponent_model().move_probabilities(
+                &Board::from_api(&game_at_turn(0, (5, 5))),
+                1,
+                &[Direction::Up]
+            ) == vec![1.0]
+        );
+    }
+}
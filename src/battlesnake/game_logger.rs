@@ -14,6 +14,9 @@ use std::fmt;
 use std::collections::{HashMap};
 use std::time::{Duration, SystemTime};
 
+use super::board::HAZARD_COST;
+use super::territory::Territory;
+use super::Board;
 use super::GameInfo;
 use super::Point;
 
@@ -184,6 +187,9 @@ pub struct GameStateLog {
     grid: GameGridLog,
     snake_legend: Vec<(usize, String, i32)>,
     game_legend: i32,
+    territory: Territory,
+    /// snake names in board order (us first) for the territory legend
+    territory_legend: Vec<String>,
 }
 
 impl GameStateLog {
@@ -198,6 +204,20 @@ impl GameStateLog {
                 .map(|(i, snake)| return (i, snake.name.clone(), snake.health))
                 .collect(),
             game_legend: data.get_turn(),
+            territory: {
+                let board = Board::from_api(data);
+                let distances = board.get_distance_maps(data.get_hazards(), HAZARD_COST);
+                Territory::from_distances(&board, &distances)
+            },
+            territory_legend: std::iter::once(data.you.name.clone())
+                .chain(
+                    data.board
+                        .snakes
+                        .iter()
+                        .filter(|snake| snake.id != data.you.id)
+                        .map(|snake| snake.name.clone()),
+                )
+                .collect(),
         }
     }
 
@@ -214,6 +234,18 @@ impl GameStateLog {
         self.grid.print();
         println!();
     }
+
+    /// Prints voronoi territory of each snake, cells marked by board index (0 is us)
+    pub fn print_territory(&self) {
+        for (i, name) in self.territory_legend.iter().enumerate() {
+            println!(
+                "[ {} ] {} - territory {} cells, {} food, {} border",
+                i, name, self.territory.size[i], self.territory.food[i], self.territory.border[i]
+            );
+        }
+        print!("{}", self.territory);
+        println!();
+    }
 }
 
 pub struct SessionStats {
@@ -569,6 +601,7 @@ mod test {
         );
         let game = GameStateLog::from_api(&gameinfo);
         game.print();
+        game.print_territory();
     }
 
     #[test]
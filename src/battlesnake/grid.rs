@@ -67,7 +67,6 @@ pub struct GameGrid {
     height: usize,
     width: usize,
     data: Vec<GridObject>,
-    ignore_hazard: bool,
 }
 
 impl GameGrid {
@@ -76,7 +75,6 @@ impl GameGrid {
             height: dimensions.0,
             width: dimensions.1,
             data: vec![GridObject::Empty; dimensions.0 * dimensions.1],
-            ignore_hazard: false,
         }
     }
 
@@ -202,10 +200,6 @@ impl GameGrid {
         }
     }
 
-    pub fn ignore_hazard(&mut self) {
-        self.ignore_hazard = true;
-    }
-
     pub fn get_width(&self) -> usize {
         self.width
     }
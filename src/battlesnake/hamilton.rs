@@ -0,0 +1,219 @@
+//! Hamiltonian cycle follower for solo games
+//!
+//! A snake following a cycle through every cell can't trap itself however long
+//! it grows. The cycle zigzags through the rows and returns along the first
+//! column, which needs an even number of rows, otherwise the board is turned
+//! by 90 degrees. With both sides odd there is no such cycle, so it's built on
+//! all rows but the last and the last row is picked up two cells at a time,
+//! leaving out the top right corner.
+//!
+//! Following the cycle strictly takes long between food, so the snake takes
+//! shortcuts towards the next food on the cycle as long as they don't overtake
+//! its own tail, with some slack for growing on the way. Once the snake fills
+//! half of the board it sticks to the cycle, unless it would starve on it.
+//!
+use super::Board;
+use super::Direction;
+use super::Point;
+
+/// Cycle positions kept between the head and the tail when taking a shortcut
+const SHORTCUT_SLACK: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct HamiltonCycle {
+    width: usize,
+    /// cells in the order of the cycle
+    cells: Vec<Point>,
+    /// position of each cell on the cycle, none for the skipped corner
+    positions: Vec<Option<usize>>,
+}
+
+/// Cycle on a board with an even height
+fn zigzag(width: usize, height: usize) -> Vec<Point> {
+    let mut cells = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let row: Vec<usize> = match y {
+            0 => (0..width).collect(),
+            y if y % 2 == 1 => (1..width).rev().collect(),
+            _ => (1..width).collect(),
+        };
+        cells.extend(row.into_iter().map(|x| Point::new(x as i32, y as i32)));
+    }
+    cells.extend((1..height).rev().map(|y| Point::new(0, y as i32)));
+    cells
+}
+
+impl HamiltonCycle {
+    /// Cycle of a board, none if the board is too narrow for one
+    pub fn new(width: usize, height: usize) -> Option<Self> {
+        if width < 2 || height < 2 {
+            return None;
+        }
+        let cells = if height % 2 == 0 {
+            zigzag(width, height)
+        } else if width % 2 == 0 {
+            zigzag(height, width)
+                .into_iter()
+                .map(|p| Point::new(p.y, p.x))
+                .collect()
+        } else {
+            // the row below the top is walked right to left, detour through
+            // the top row above every other pair of its cells
+            let top = height as i32 - 1;
+            let mut cells: Vec<Point> = Vec::with_capacity(width * height - 1);
+            for p in zigzag(width, height - 1) {
+                if let Some(last) = cells.last().cloned() {
+                    if last.y == top - 1 && p.y == top - 1 && last.x == p.x + 1 && p.x % 2 == 0 {
+                        cells.push(Point::new(last.x, top));
+                        cells.push(Point::new(p.x, top));
+                    }
+                }
+                cells.push(p);
+            }
+            cells
+        };
+        let mut positions = vec![None; width * height];
+        for (i, p) in cells.iter().enumerate() {
+            positions[p.y as usize * width + p.x as usize] = Some(i);
+        }
+        Some(Self {
+            width,
+            cells,
+            positions,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn get_position(&self, p: &Point) -> Option<usize> {
+        if p.x < 0 || p.y < 0 || p.x as usize >= self.width {
+            return None;
+        }
+        self.positions
+            .get(p.y as usize * self.width + p.x as usize)
+            .cloned()
+            .flatten()
+    }
+
+    /// Next move of snake `index`, none if it is off the cycle or boxed in
+    pub fn get_move(&self, board: &Board, index: usize) -> Option<Direction> {
+        let snake = &board.snakes[index];
+        let head = snake.head();
+        let n = self.len();
+        let start = self.get_position(&head)?;
+        let ahead = |p: &Point| self.get_position(p).map(|i| (i + n - start) % n);
+
+        // the body lies behind the head, the closest part of it marks the tail
+        let mut tail = n;
+        for p in snake.body.nodes.iter().skip(1) {
+            match ahead(p)? {
+                0 => (),
+                i => tail = tail.min(i),
+            }
+        }
+        let target = board
+            .food
+            .iter()
+            .filter_map(ahead)
+            .filter(|i| *i > 0)
+            .min()
+            .filter(|i| snake.size() * 2 <= n || *i > snake.health as usize);
+
+        board
+            .get_pruned_moves(&head, 1)
+            .into_iter()
+            .filter_map(|dir| ahead(&(head + dir)).map(|i| (dir, i)))
+            .filter(|(_, i)| {
+                *i == 1 || matches!(target, Some(t) if *i <= t && *i + SHORTCUT_SLACK < tail)
+            })
+            .max_by_key(|(_, i)| *i)
+            .map(|(dir, _)| dir)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::GameInfo;
+    use super::*;
+
+    fn check(width: usize, height: usize) {
+        let cycle = HamiltonCycle::new(width, height).unwrap();
+        let mut seen = vec![false; width * height];
+        for (i, p) in cycle.cells.iter().enumerate() {
+            let next = cycle.cells[(i + 1) % cycle.len()];
+            assert!(
+                p.is_neighbour(next),
+                "{}x{}: {} to {}",
+                width,
+                height,
+                p,
+                next
+            );
+            assert_eq!(cycle.get_position(p), Some(i));
+            assert!(!seen[p.y as usize * width + p.x as usize]);
+            seen[p.y as usize * width + p.x as usize] = true;
+        }
+        if width % 2 == 1 && height % 2 == 1 {
+            assert_eq!(cycle.len(), width * height - 1);
+            let corner = Point::new(width as i32 - 1, height as i32 - 1);
+            assert_eq!(cycle.get_position(&corner), None);
+        } else {
+            assert_eq!(cycle.len(), width * height);
+        }
+    }
+
+    #[test]
+    fn cycles() {
+        check(4, 4);
+        check(7, 2);
+        check(2, 7);
+        check(5, 3);
+        check(11, 11);
+        check(19, 19);
+        check(7, 25);
+        assert!(HamiltonCycle::new(1, 5).is_none());
+    }
+
+    #[test]
+    fn follow() {
+        let state = |food: &str, body: &str| {
+            GameInfo::new(&format!(
+                r#"{{
+                "game": {{"id": "game-1", "timeout": 500}},
+                "turn": 5,
+                "board": {{
+                    "height": 6,
+                    "width": 6,
+                    "food": [{}],
+                    "hazards": [],
+                    "snakes": [
+                        {{"id": "me", "name": "me", "health": 90, "body": [{}], "head": {{"x": 3, "y": 0}}, "length": 4, "shout": ""}}
+                    ]
+                }},
+                "you": {{"id": "me", "name": "me", "health": 90, "body": [{}], "head": {{"x": 3, "y": 0}}, "length": 4, "shout": ""}}
+            }}"#,
+                food, body, body
+            ))
+        };
+        let cycle = HamiltonCycle::new(6, 6).unwrap();
+        // heading right along the first row
+        let body = r#"{"x": 3, "y": 0}, {"x": 2, "y": 0}, {"x": 1, "y": 0}, {"x": 0, "y": 0}"#;
+        let board = Board::from_api(&state(r#"{"x": 3, "y": 3}"#, body));
+        assert_eq!(cycle.get_move(&board, 0), Some(Direction::Up));
+        // no shortcuts without food, nor past it
+        let board = Board::from_api(&state("", body));
+        assert_eq!(cycle.get_move(&board, 0), Some(Direction::Right));
+        let board = Board::from_api(&state(r#"{"x": 5, "y": 1}"#, body));
+        assert_eq!(cycle.get_move(&board, 0), Some(Direction::Right));
+        // the tail is just above, a shortcut would run into it
+        let body = r#"{"x": 3, "y": 0}, {"x": 2, "y": 0}, {"x": 2, "y": 1}, {"x": 3, "y": 1}"#;
+        let board = Board::from_api(&state(r#"{"x": 3, "y": 3}"#, body));
+        assert_eq!(cycle.get_move(&board, 0), Some(Direction::Right));
+        // off the cycle
+        let mut board = Board::from_api(&state("", body));
+        board.snakes[0].body.nodes[0] = Point::new(6, 0);
+        assert_eq!(cycle.get_move(&board, 0), None);
+    }
+}
@@ -1,13 +1,22 @@
 use super::point::Point;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Ruleset {
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GameData {
     pub id: String,
+    #[serde(default)]
+    pub ruleset: Ruleset,
     pub timeout: i32,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Board {
     pub height: i32,
     pub width: i32,
@@ -16,7 +25,7 @@ pub struct Board {
     pub snakes: Vec<ApiSnake>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ApiSnake {
     pub id: String,
     pub name: String,
@@ -29,7 +38,7 @@ pub struct ApiSnake {
     // squad: String
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GameInfo {
     pub game: GameData,
     pub turn: i32,
@@ -111,6 +120,10 @@ impl GameInfo {
         self.game.id.clone()
     }
 
+    pub fn get_ruleset(&self) -> &str {
+        self.game.ruleset.name.as_ref()
+    }
+
     pub fn get_my_length(&self) -> i32 {
         self.you.length
     }
@@ -181,6 +194,7 @@ mod test {
 					"shout":""}
 			}"#;
         let gameinfo = GameInfo::new(&data);
+        assert_eq!(gameinfo.get_ruleset(), "solo");
         dbg!(gameinfo);
     }
 }
@@ -0,0 +1,221 @@
+//! Immediate lethality of moves
+//!
+//! The last check before a move goes out, whichever strategy picked it. The
+//! four directions are ranked by what happens on the very next turn: running
+//! into a wall or a body is certain death, as is running out of health, a
+//! head to head with a snake at least as long is likely death. A move is
+//! replaced only by a less lethal one, so the strategy keeps its choice among
+//! equally safe moves. The replacement is the least lethal move that leaves a
+//! way back to our tail, if there is one, with the best score of the search.
+//!
+use std::cmp::Ordering;
+
+use super::Board;
+use super::Direction;
+use super::Point;
+use super::ALL_DIRECTIONS;
+
+/// Outcome of a move on the next turn, from safe to most certain death
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Lethality {
+    Safe,
+    HeadToHead,
+    Starvation,
+    Body,
+    Wall,
+}
+
+impl Board {
+    /// Lethality of moving snake `index` in `dir`, a turn in hazard costs
+    /// `hazard_damage` on top of the regular turn
+    pub fn get_lethality(
+        &self,
+        index: usize,
+        dir: Direction,
+        hazards: &[Point],
+        hazard_damage: f32,
+    ) -> Lethality {
+        let snake = &self.snakes[index];
+        let p = snake.head() + dir;
+        if !self.is_inbounds(&p) {
+            return Lethality::Wall;
+        }
+        if !self.is_free(&p, 1) {
+            return Lethality::Body;
+        }
+        if !self.food.contains(&p) {
+            let damage = if hazards.contains(&p) {
+                hazard_damage
+            } else {
+                0.0
+            };
+            if snake.health as f32 - 1.0 - damage <= 0.0 {
+                return Lethality::Starvation;
+            }
+        }
+        let contested = self.snakes.iter().enumerate().any(|(i, other)| {
+            i != index && other.size() >= snake.size() && other.head().is_neighbour(p)
+        });
+        if contested {
+            return Lethality::HeadToHead;
+        }
+        Lethality::Safe
+    }
+
+    /// All moves of snake `index`, least lethal first
+    pub fn rank_moves(
+        &self,
+        index: usize,
+        hazards: &[Point],
+        hazard_damage: f32,
+    ) -> Vec<(Direction, Lethality)> {
+        let mut moves: Vec<(Direction, Lethality)> = ALL_DIRECTIONS
+            .iter()
+            .map(|dir| (*dir, self.get_lethality(index, *dir, hazards, hazard_damage)))
+            .collect();
+        moves.sort_by_key(|(_, lethality)| *lethality);
+        moves
+    }
+
+    /// Keeps `dir` of snake `index` unless another move is less lethal.
+    /// Among the least lethal moves safe ones come first, then the best
+    /// of `scores`, indexed by `Direction::as_index`.
+    pub fn validate_move(
+        &self,
+        index: usize,
+        dir: Direction,
+        hazards: &[Point],
+        hazard_damage: f32,
+        scores: &[Option<f32>; 4],
+    ) -> Direction {
+        let ranked = self.rank_moves(index, hazards, hazard_damage);
+        let least = ranked[0].1;
+        if self.get_lethality(index, dir, hazards, hazard_damage) <= least {
+            return dir;
+        }
+        let key = |d: &Direction| {
+            (
+                self.is_safe_move(index, *d),
+                scores[d.as_index()].unwrap_or(f32::MIN),
+            )
+        };
+        ranked
+            .iter()
+            .filter(|(_, lethality)| *lethality == least)
+            .map(|(d, _)| *d)
+            .max_by(|a, b| key(a).partial_cmp(&key(b)).unwrap_or(Ordering::Equal))
+            .unwrap_or(dir)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::GameInfo;
+    use super::*;
+
+    #[test]
+    fn ranking() {
+        let state = r#"{
+            "game": {"id": "game-1", "timeout": 500},
+            "turn": 5,
+            "board": {
+                "height": 4,
+                "width": 5,
+                "food": [],
+                "hazards": [{"x": 0, "y": 1}],
+                "snakes": [
+                    {"id": "me", "name": "me", "health": 10, "body": [{"x": 1, "y": 1}, {"x": 1, "y": 0}, {"x": 2, "y": 0}], "head": {"x": 1, "y": 1}, "length": 3, "shout": ""},
+                    {"id": "other", "name": "other", "health": 90, "body": [{"x": 1, "y": 3}, {"x": 2, "y": 3}, {"x": 3, "y": 3}, {"x": 4, "y": 3}], "head": {"x": 1, "y": 3}, "length": 4, "shout": ""}
+                ]
+            },
+            "you": {"id": "me", "name": "me", "health": 10, "body": [{"x": 1, "y": 1}, {"x": 1, "y": 0}, {"x": 2, "y": 0}], "head": {"x": 1, "y": 1}, "length": 3, "shout": ""}
+        }"#;
+        let gameinfo = GameInfo::new(state);
+        let hazards = gameinfo.get_hazards().clone();
+        let mut board = Board::from_api(&gameinfo);
+        assert_eq!(
+            board.rank_moves(0, &hazards, 15.0),
+            vec![
+                (Direction::Right, Lethality::Safe),
+                (Direction::Up, Lethality::HeadToHead),
+                (Direction::Left, Lethality::Starvation),
+                (Direction::Down, Lethality::Body)
+            ]
+        );
+        assert_eq!(
+            board.validate_move(0, Direction::Up, &hazards, 15.0, &[None; 4]),
+            Direction::Right
+        );
+        assert_eq!(
+            board.validate_move(0, Direction::Right, &hazards, 15.0, &[None; 4]),
+            Direction::Right
+        );
+        // the longer snake doesn't fear our head
+        assert_eq!(
+            board.get_lethality(1, Direction::Up, &hazards, 15.0),
+            Lethality::Wall
+        );
+        assert_eq!(
+            board.get_lethality(1, Direction::Down, &hazards, 15.0),
+            Lethality::Safe
+        );
+
+        // harmless hazard doesn't starve us either
+        assert_eq!(
+            board.get_lethality(0, Direction::Left, &hazards, 0.0),
+            Lethality::Safe
+        );
+        // then the better scored of the safe moves replaces the head to head
+        let scores = [Some(0.2), Some(0.4), Some(0.9), None];
+        assert_eq!(
+            board.validate_move(0, Direction::Up, &hazards, 0.0, &scores),
+            Direction::Left
+        );
+
+        // food in the hazard keeps us alive, as safe as going right
+        board.food.push(Point::new(0, 1));
+        assert_eq!(
+            board.get_lethality(0, Direction::Left, &hazards, 15.0),
+            Lethality::Safe
+        );
+        assert_eq!(
+            board.validate_move(0, Direction::Left, &hazards, 15.0, &[None; 4]),
+            Direction::Left
+        );
+    }
+
+    #[test]
+    fn tail_safety() {
+        // going up leads into a pocket closed by the other snake and our own body
+        let state = r#"{
+            "game": {"id": "game-1", "timeout": 500},
+            "turn": 5,
+            "board": {
+                "height": 5,
+                "width": 5,
+                "food": [],
+                "hazards": [],
+                "snakes": [
+                    {"id": "me", "name": "me", "health": 90, "body": [
+                        {"x": 0, "y": 1}, {"x": 1, "y": 1}, {"x": 1, "y": 2}, {"x": 1, "y": 3}, {"x": 2, "y": 3}
+                    ], "head": {"x": 0, "y": 1}, "length": 5, "shout": ""},
+                    {"id": "other", "name": "other", "health": 90, "body": [
+                        {"x": 0, "y": 3}, {"x": 0, "y": 4}, {"x": 1, "y": 4}, {"x": 2, "y": 4}
+                    ], "head": {"x": 0, "y": 3}, "length": 4, "shout": ""}
+                ]
+            },
+            "you": {"id": "me", "name": "me", "health": 90, "body": [
+                {"x": 0, "y": 1}, {"x": 1, "y": 1}, {"x": 1, "y": 2}, {"x": 1, "y": 3}, {"x": 2, "y": 3}
+            ], "head": {"x": 0, "y": 1}, "length": 5, "shout": ""}
+        }"#;
+        let board = Board::from_api(&GameInfo::new(state));
+        assert_eq!(board.get_lethality(0, Direction::Up, &[], 15.0), Lethality::Safe);
+        assert!(!board.is_safe_move(0, Direction::Up));
+        // running into our body, the way out wins over the better score
+        let scores = [None, None, Some(0.9), Some(0.1)];
+        assert_eq!(
+            board.validate_move(0, Direction::Right, &[], 15.0, &scores),
+            Direction::Down
+        );
+    }
+}
@@ -1,22 +1,329 @@
 use log::*;
 use std::cmp::{Eq, Ord, Ordering, PartialEq, PartialOrd};
+use std::convert::TryFrom;
 use std::collections::BinaryHeap;
-use std::sync::Mutex;
+use std::fmt;
+use std::str::FromStr;
 use std::time::{Duration, SystemTime};
 
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use super::{Board, CauseOfDeath};
+use super::config::Settings;
+use super::evaluator::{Evaluator, WIN_SCORE};
+use super::game::{GameContext, SearchMemory};
+use super::hamilton::HamiltonCycle;
+use super::opponent::{softmax_probabilities, OpponentModel};
+use super::spacefill::SurvivalSolver;
 use super::Move;
 use super::GameInfo;
-use super::Direction;
 use super::Point;
+use super::{Direction, ALL_DIRECTIONS};
+
+/// How outcomes of opponents' replies to our move are combined
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum SearchMode {
+    /// assume the worst reply
+    Paranoid,
+    /// weigh replies by their probability
+    Expectimax,
+    /// `alpha * paranoid + (1 - alpha) * expectimax`
+    Mixed(f32),
+}
+
+/// Where opponents' move probabilities come from
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum MovePolicy {
+    Uniform,
+    /// softmax over a quick move evaluation with given temperature
+    Softmax(f32),
+    /// learned opponent behaviour, uniform until observed
+    Learned,
+}
+
+impl MovePolicy {
+    fn probabilities(
+        &self,
+        board: &Board,
+        index: usize,
+        moves: &[Direction],
+        model: &OpponentModel,
+    ) -> Vec<f32> {
+        match self {
+            MovePolicy::Uniform => vec![1.0 / moves.len() as f32; moves.len()],
+            MovePolicy::Softmax(t) => softmax_probabilities(board, index, moves, *t),
+            MovePolicy::Learned => model.move_probabilities(board, index, moves),
+        }
+    }
+}
+
+impl FromStr for SearchMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split(':').collect::<Vec<&str>>().as_slice() {
+            ["paranoid"] => Ok(SearchMode::Paranoid),
+            ["expectimax"] => Ok(SearchMode::Expectimax),
+            ["mixed", alpha] => match alpha.parse::<f32>() {
+                Ok(a) if (0.0..=1.0).contains(&a) => Ok(SearchMode::Mixed(a)),
+                _ => Err(format!("Invalid mixed search alpha \"{}\", expected 0.0 - 1.0", alpha)),
+            },
+            _ => Err(format!("Unknown search mode \"{}\"", s)),
+        }
+    }
+}
+
+impl FromStr for MovePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split(':').collect::<Vec<&str>>().as_slice() {
+            ["uniform"] => Ok(MovePolicy::Uniform),
+            ["learned"] => Ok(MovePolicy::Learned),
+            ["softmax"] => Ok(MovePolicy::Softmax(1.0)),
+            ["softmax", t] => match t.parse::<f32>() {
+                Ok(t) if t > 0.0 => Ok(MovePolicy::Softmax(t)),
+                _ => Err(format!("Invalid softmax temperature \"{}\"", t)),
+            },
+            _ => Err(format!("Unknown move policy \"{}\"", s)),
+        }
+    }
+}
+
+impl fmt::Display for SearchMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SearchMode::Paranoid => write!(f, "paranoid"),
+            SearchMode::Expectimax => write!(f, "expectimax"),
+            SearchMode::Mixed(alpha) => write!(f, "mixed:{}", alpha),
+        }
+    }
+}
+
+impl fmt::Display for MovePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MovePolicy::Uniform => write!(f, "uniform"),
+            MovePolicy::Softmax(t) => write!(f, "softmax:{}", t),
+            MovePolicy::Learned => write!(f, "learned"),
+        }
+    }
+}
+
+impl TryFrom<String> for SearchMode {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl TryFrom<String> for MovePolicy {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<SearchMode> for String {
+    fn from(mode: SearchMode) -> Self {
+        mode.to_string()
+    }
+}
+
+impl From<MovePolicy> for String {
+    fn from(policy: MovePolicy) -> Self {
+        policy.to_string()
+    }
+}
+
+/// Scores of our death by its cause, all have to be negative
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeathScores {
+    pub head_to_head: f32,
+    pub out_of_health: f32,
+    pub other: f32,
+}
+
+impl Default for DeathScores {
+    fn default() -> Self {
+        Self {
+            head_to_head: -1.0,
+            out_of_health: -2.0,
+            other: -3.0,
+        }
+    }
+}
+
+impl DeathScores {
+    pub fn get(&self, cause: &CauseOfDeath) -> f32 {
+        match cause {
+            CauseOfDeath::HeadToHead => self.head_to_head,
+            CauseOfDeath::OutOfHealth => self.out_of_health,
+            _ => self.other,
+        }
+    }
+
+    /// Score of the least bad death
+    pub fn max(&self) -> f32 {
+        self.head_to_head.max(self.out_of_health).max(self.other)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchParams {
+    pub mode: SearchMode,
+    pub policy: MovePolicy,
+    /// stop at the best state this deep, search until the time runs out if unset
+    pub max_depth: Option<usize>,
+    pub death_scores: DeathScores,
+}
+
+impl Default for SearchParams {
+    fn default() -> Self {
+        Self {
+            mode: SearchMode::Paranoid,
+            policy: MovePolicy::Learned,
+            max_depth: None,
+            death_scores: DeathScores::default(),
+        }
+    }
+}
+
+impl SearchParams {
+    /// Sets mode and policy from `<mode>[/<policy>]`, e.g. `mixed:0.5/softmax:2.0`
+    pub fn set_from_str(&mut self, s: &str) -> Result<(), String> {
+        let mut parts = s.splitn(2, '/');
+        let mode = parts.next().unwrap_or("").parse::<SearchMode>()?;
+        let policy = match parts.next() {
+            Some(policy) => policy.parse::<MovePolicy>()?,
+            None => SearchParams::default().policy,
+        };
+        self.mode = mode;
+        self.policy = policy;
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let scores = &self.death_scores;
+        if [scores.head_to_head, scores.out_of_health, scores.other]
+            .iter()
+            .any(|s| s.is_nan() || *s >= 0.0)
+        {
+            return Err("Death scores have to be negative".to_string());
+        }
+        if self.max_depth == Some(0) {
+            return Err("Max depth has to be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Parses `<mode>[/<policy>]`, e.g. `mixed:0.5/softmax:2.0`
+impl FromStr for SearchParams {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut params = SearchParams::default();
+        params.set_from_str(s)?;
+        Ok(params)
+    }
+}
+
+impl fmt::Display for SearchParams {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.mode, self.policy)?;
+        if let Some(depth) = self.max_depth {
+            write!(f, " depth<={}", depth)?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of a single joint move
+struct Outcome {
+    board: Board,
+    h: f32,
+    /// probability of opponents' replies
+    p: f32,
+    alive: bool,
+    /// our kill moves on the board, as found by the evaluator
+    kills: Vec<Direction>,
+}
+
+/// Outcomes of one of our moves aggregated over all opponents' replies
+struct MoveSummary {
+    worst: Option<Outcome>,
+    likely: Option<Outcome>,
+    weighted_h: f32,
+    total_p: f32,
+}
+
+impl MoveSummary {
+    fn new() -> Self {
+        Self {
+            worst: None,
+            likely: None,
+            weighted_h: 0.0,
+            total_p: 0.0,
+        }
+    }
+
+    fn add(&mut self, outcome: Outcome) {
+        self.weighted_h += outcome.p * outcome.h;
+        self.total_p += outcome.p;
+        let is_likely = outcome.alive && !matches!(&self.likely, Some(l) if outcome.p <= l.p);
+        let is_worst = !matches!(&self.worst, Some(w) if outcome.h >= w.h);
+        if is_likely && is_worst {
+            self.worst = Some(Outcome {
+                board: outcome.board.clone(),
+                kills: outcome.kills.clone(),
+                ..outcome
+            });
+            self.likely = Some(outcome);
+        } else if is_likely {
+            self.likely = Some(outcome);
+        } else if is_worst {
+            self.worst = Some(outcome);
+        }
+    }
+
+    /// Score of the move, board to continue the search from and our kill moves on it
+    fn resolve(self, mode: SearchMode) -> Option<(f32, Board, Vec<Direction>)> {
+        let worst = self.worst?;
+        let expected = if self.total_p > 0.0 {
+            self.weighted_h / self.total_p
+        } else {
+            worst.h
+        };
+        let (h, paranoid_board) = match mode {
+            SearchMode::Paranoid => (worst.h, true),
+            SearchMode::Expectimax => (expected, false),
+            SearchMode::Mixed(alpha) => (alpha * worst.h + (1.0 - alpha) * expected, alpha >= 0.5),
+        };
+        match self.likely {
+            Some(likely) if !paranoid_board => Some((h, likely.board, likely.kills)),
+            _ => Some((h, worst.board, worst.kills)),
+        }
+    }
+}
 
 struct State {
     board: Board,
-    root: Option<Direction>,
+    /// our moves leading to this state, first one is the root decision
+    line: Vec<Direction>,
     depth: usize,
     h: f32,
+    /// reached by a kill move, expanded first among equal scores
+    kill: bool,
+    /// our kill moves on the board
+    kills: Vec<Direction>,
 }
 
 impl Ord for State {
@@ -27,7 +334,9 @@ impl Ord for State {
 
 impl PartialOrd for State {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.h.partial_cmp(&other.h)
+        self.h
+            .partial_cmp(&other.h)
+            .map(|o| o.then(self.kill.cmp(&other.kill)))
     }
 }
 
@@ -35,7 +344,7 @@ impl Eq for State {}
 
 impl PartialEq for State {
     fn eq(&self, other: &Self) -> bool {
-        self.h == other.h
+        self.h == other.h && self.kill == other.kill
     }
 }
 
@@ -66,74 +375,115 @@ fn min_f32(a: f32, b: f32) -> f32 {
     }
 }
 
+/// All moves of all snakes, our kill moves first and opponents' moves ordered
+/// and pruned by the opponent model
 #[inline]
-fn max_f32(a: f32, b: f32) -> f32 {
-    match a.partial_cmp(&b).unwrap() {
-        Ordering::Greater => a,
-        _ => b,
-    }
+fn get_ordered_moves(
+    board: &Board,
+    model: &OpponentModel,
+    kills: &[Direction],
+) -> Vec<Vec<Direction>> {
+    board
+        .get_all_moves()
+        .iter()
+        .enumerate()
+        .map(|(i, moves)| {
+            if i == 0 {
+                let mut moves = moves.clone();
+                moves.sort_by_key(|dir| !kills.contains(dir));
+                moves
+            } else {
+                model.order_moves(board, i, moves)
+            }
+        })
+        .collect()
 }
 
-pub fn heuristic(board: &Board, snake_index: usize, hazards: &Vec<Point>) -> f32 {
-    // floodfill / area dominance
-    // A* 1.0 - (cost / hp)
-    // aggression
-    if snake_index == 0 && board.snakes.len() == 1 {
-        return 2.0;
-    }
-
-    let snake_len = board.snakes[snake_index].size();
-    let mut tot_lens = 0;
-    for s in board.snakes.iter() {
-        tot_lens += s.size();
-    }
-    let len_score = snake_len as f32 / tot_lens as f32;
-
-    let mut aval: f32 = if board.food.is_empty() {1.0} else {0.0};
-    for food in board.food.iter() {
-        let res = board.astar(board.snakes[snake_index].head(), *food, hazards);
-        if res.is_some() {
-            let (g_score, _) = res.unwrap();
-            // println!("{} {} -> rat {} -> {}")
-            let hp = board.snakes[snake_index].health as usize;
-            if g_score <= hp {
-                let new_val = (hp - g_score) as f32 / hp as f32;
-                aval = max_f32(aval, new_val);
-            }
-            }
-    }
+#[inline]
+fn extend_line(line: &[Direction], dir: Direction) -> Vec<Direction> {
+    let mut new_line = line.to_vec();
+    new_line.push(dir);
+    new_line
+}
 
-    let mut aggression: f32 = 0.1;
-    for snake in board.snakes.iter() {
-        if snake.size() < snake_len {
-            if snake.head() == board.snakes[snake_index].head() {
-                aggression = 1.1;
-            } else {
-                aggression = 1.0 / snake.head().manhattan_distance(&board.snakes[snake_index].head()) as f32;
-            }
+/// Move of a solo game or of a snake with nowhere left to go, where there is
+/// nothing to search for against opponents
+fn get_endgame_move(
+    board: &Board,
+    hazards: &[Point],
+    hazard_damage: f32,
+) -> Option<Direction> {
+    if board.snakes.len() == 1 {
+        // hazards would drain the health on the cycle
+        if !hazards.is_empty() {
+            return None;
         }
+        let cycle = HamiltonCycle::new(board.get_width(), board.get_height())?;
+        return cycle.get_move(board, 0);
+    }
+    let distances = board.get_distance_maps(hazards, 1);
+    if board.is_sealed(&distances, 0) || board.is_trapped(&distances, 0) {
+        return SurvivalSolver::new(hazard_damage)
+            .solve(board, 0, hazards)
+            .map(|(dir, _)| dir);
     }
-    //finally get the ratio. should implement here a weighted ratio
-    aval * aggression * len_score
+    None
 }
 
-pub fn get_move(gameinfo: &GameInfo, time_budget: Duration) -> Move {
+pub fn get_move(
+    gameinfo: &GameInfo,
+    ctx: &mut GameContext,
+    time_budget: Duration,
+    settings: &Settings,
+    evaluator: &dyn Evaluator,
+) -> Move {
     let time_start = SystemTime::now();
+    let params = &settings.search;
     let initial_board = Board::from_api(gameinfo);
     let hazards = gameinfo.get_hazards();
     let mut cnt_explored: usize = 0;
-    let mut decision = initial_board.snakes[0].get_default_move();
+    let mut max_depth: usize = 0;
+    let mut root_scores: [Option<f32>; 4] = [None; 4];
+    let model = ctx.opponent_model();
+    // prefer continuing last turn's plan until the search finds something better
+    let my_moves = initial_board.get_pruned_moves(&initial_board.snakes[0].head(), 1);
+    let mut decision = match ctx.planned_move() {
+        Some(dir) if my_moves.contains(&dir) => dir,
+        _ => initial_board.snakes[0].get_default_move(),
+    };
+    let mut line: Vec<Direction> = vec![decision];
+
+    if let Some(dir) = get_endgame_move(&initial_board, hazards, settings.evaluator.hazard_damage) {
+        ctx.set_plan(
+            vec![dir],
+            SearchMemory {
+                turn: gameinfo.get_turn(),
+                ..SearchMemory::default()
+            },
+        );
+        return Move {
+            movement: dir,
+            shout: None,
+        };
+    }
 
     let mut queue: BinaryHeap<State> = BinaryHeap::new();
     queue.push(State {
-        board: initial_board,
-        root: None,
+        board: initial_board.clone(),
+        line: Vec::new(),
         depth: 0,
         h: 1.0,
+        kill: false,
+        kills: evaluator
+            .evaluate_with_kills(&initial_board, 0, hazards)
+            .1
+            .unwrap_or_default(),
     });
     'minimax: while let Some(first) = queue.pop() {
-        if let Some(dir) = first.root {
-            decision = dir;
+        if let Some(dir) = first.line.first() {
+            decision = *dir;
+            line = first.line.clone();
+            max_depth = max_depth.max(first.depth);
         }
 
         if SystemTime::now().duration_since(time_start).unwrap() >= time_budget {
@@ -147,59 +497,89 @@ pub fn get_move(gameinfo: &GameInfo, time_budget: Duration) -> Move {
             // );
             break 'minimax;
         }
+        if matches!(params.max_depth, Some(depth) if first.depth >= depth) {
+            // states are popped best first, this is the best line that deep
+            break 'minimax;
+        }
 
-        let all_snakes_moves = first.board.get_all_moves();
-
-        // prune possible moves
-
-        let worst_outcomes: Mutex<[Option<State>; 4]> = Mutex::new([None, None, None, None]);
+        let all_snakes_moves = get_ordered_moves(&first.board, &model, &first.kills);
+        let probabilities: Vec<Vec<f32>> = all_snakes_moves
+            .iter()
+            .enumerate()
+            .map(|(i, moves)| {
+                if i == 0 {
+                    vec![1.0; moves.len()]
+                } else {
+                    params.policy.probabilities(&first.board, i, moves, &model)
+                }
+            })
+            .collect();
 
         let possible_moves = cartesian_product(&all_snakes_moves);
         cnt_explored += possible_moves.len();
 
-        possible_moves.par_iter().for_each(|moves| {
-            let mut new_board = first.board.clone();
-            let dead_snakes = new_board.advance_snakes(moves, &hazards);
-            let my_move = *moves.get(0).unwrap();
-            let my_move_index = my_move.as_index();
+        let outcomes: Vec<(Direction, Outcome)> = possible_moves
+            .par_iter()
+            .map(|moves| {
+                let mut new_board = first.board.clone();
+                let dead_snakes = new_board.advance_snakes(moves, hazards);
+                let my_move = moves[0];
+                let p = moves
+                    .iter()
+                    .enumerate()
+                    .skip(1)
+                    .map(|(i, dir)| {
+                        let j = all_snakes_moves[i].iter().position(|d| d == dir).unwrap();
+                        probabilities[i][j]
+                    })
+                    .product();
 
-            if let Some(&cod) = dead_snakes.get(&0) {
-                worst_outcomes.lock().unwrap()[my_move_index] = Some(State {
-                    board: new_board,
-                    root: Some(first.root.unwrap_or(my_move)),
-                    depth: first.depth + 1,
-                    h: match cod {
-                        CauseOfDeath::HeadToHead => -1.0,
-                        CauseOfDeath::OutOfHealth => -2.0,
-                        _ => -3.0,
-                    },
-                });
-            } else {
-                let next_h_score = heuristic(&new_board, 0, hazards);
-                let is_new_worst = worst_outcomes
-                    .lock()
-                    .unwrap()
-                    .get(my_move_index)
-                    .unwrap()
-                    .as_ref()
-                    .map(|worst_outcome| next_h_score < worst_outcome.h)
-                    .unwrap_or(true);
-
-                if is_new_worst {
-                    worst_outcomes.lock().unwrap()[my_move_index] = Some(State {
+                let (h, alive, kills) = match dead_snakes.get(&0) {
+                    Some(cod) => (params.death_scores.get(cod), false, None),
+                    None => {
+                        let (h, kills) = evaluator.evaluate_with_kills(&new_board, 0, hazards);
+                        (h, true, kills)
+                    }
+                };
+                (
+                    my_move,
+                    Outcome {
                         board: new_board,
-                        root: Some(first.root.unwrap_or(my_move)),
-                        depth: first.depth + 1,
-                        h: min_f32(next_h_score, first.h),
-                    });
-                }
-            };
-        });
+                        h,
+                        p,
+                        alive,
+                        kills: kills.unwrap_or_default(),
+                    },
+                )
+            })
+            .collect();
+
+        let mut summaries: [Option<MoveSummary>; 4] = [None, None, None, None];
+        for (my_move, outcome) in outcomes {
+            summaries[my_move.as_index()]
+                .get_or_insert_with(MoveSummary::new)
+                .add(outcome);
+        }
 
-        for worst_outcome in worst_outcomes.lock().unwrap().iter_mut() {
-            if let Some(state) = worst_outcome.take() {
+        for (i, summary) in summaries.iter_mut().enumerate() {
+            if let Some((h, board, kills)) = summary.take().and_then(|s| s.resolve(params.mode)) {
+                // a won line isn't capped by the scores on the way there
+                let won = h >= WIN_SCORE;
+                let state = State {
+                    board,
+                    line: extend_line(&first.line, ALL_DIRECTIONS[i]),
+                    depth: first.depth + 1,
+                    h: if won { h } else { min_f32(h, first.h) },
+                    kill: first.kills.contains(&ALL_DIRECTIONS[i]),
+                    kills,
+                };
+                if state.depth == 1 {
+                    root_scores[i] = Some(state.h);
+                } else if won {
+                    root_scores[state.line[0].as_index()] = Some(state.h);
+                }
                 if state.depth < 3 {
-                    println!("Depth 1 option: dir={:?} score={}", state.root, state.h);
+                    trace!("Depth 1 option: dir={:?} score={}", state.line.first(), state.h);
                 }
                 if state.h >= 0.0 {
                     queue.push(state);
@@ -208,6 +588,24 @@ pub fn get_move(gameinfo: &GameInfo, time_budget: Duration) -> Move {
         }
     }
 
+    // the search doesn't look far enough to see the snake boxing itself in
+    let safe = initial_board.veto_move(0, decision, &root_scores, &params.death_scores);
+    if safe != decision {
+        debug!("Vetoed {:?} without a way back to our tail, taking {:?}", decision, safe);
+        decision = safe;
+        line = vec![safe];
+    }
+
+    ctx.set_plan(
+        line,
+        SearchMemory {
+            turn: gameinfo.get_turn(),
+            root_scores,
+            depth: max_depth,
+            explored: cnt_explored,
+        },
+    );
+
     Move {
         movement: decision,
         shout: None,
@@ -216,9 +614,162 @@ pub fn get_move(gameinfo: &GameInfo, time_budget: Duration) -> Move {
 
 #[cfg(test)]
 mod test {
+    use super::super::evaluator::WeightedEvaluator;
     use super::super::game_logger::GameStateLog;
     use super::*;
 
+    #[test]
+    fn search_params() {
+        let params: SearchParams = "mixed:0.25/softmax:2".parse().unwrap();
+        assert_eq!(params.mode, SearchMode::Mixed(0.25));
+        assert_eq!(params.policy, MovePolicy::Softmax(2.0));
+        assert_eq!(format!("{}", params), "mixed:0.25/softmax:2");
+        let params: SearchParams = "expectimax".parse().unwrap();
+        assert_eq!(params.policy, MovePolicy::Learned);
+        assert!("mixed:1.5".parse::<SearchParams>().is_err());
+        assert!("greedy".parse::<SearchParams>().is_err());
+        assert!("paranoid/softmax:0".parse::<SearchParams>().is_err());
+
+        let params: SearchParams = serde_json::from_str(
+            r#"{"mode": "mixed:0.5", "max_depth": 4, "death_scores": {"head_to_head": -0.5}}"#,
+        )
+        .unwrap();
+        assert_eq!(params.mode, SearchMode::Mixed(0.5));
+        assert_eq!(params.policy, MovePolicy::Learned);
+        assert_eq!(params.death_scores.get(&CauseOfDeath::HeadToHead), -0.5);
+        assert_eq!(params.death_scores.get(&CauseOfDeath::OutOfHealth), -2.0);
+        assert!(params.validate().is_ok());
+        assert_eq!(
+            serde_json::from_str::<SearchParams>(&serde_json::to_string(&params).unwrap()).unwrap(),
+            params
+        );
+        assert!(serde_json::from_str::<SearchParams>(r#"{"mode": "greedy"}"#).is_err());
+        let params: SearchParams = serde_json::from_str(r#"{"death_scores": {"other": 0.0}}"#).unwrap();
+        assert!(params.validate().is_err());
+    }
+
+    #[test]
+    fn move_summary() {
+        let board = Board::from_api(&GameInfo::new(
+            r#"{
+            "game": {"id": "game-1", "timeout": 500},
+            "turn": 5,
+            "board": {
+                "height": 11,
+                "width": 11,
+                "food": [],
+                "hazards": [],
+                "snakes": [
+                    {
+                        "id": "me", "name": "Go  Giddy", "health": 90,
+                        "body": [{"x": 9, "y": 1}, {"x": 9, "y": 2}, {"x": 9, "y": 3}],
+                        "head": {"x": 9, "y": 1}, "length": 3, "shout": ""
+                    }
+                ]
+            },
+            "you": {
+                "id": "me", "name": "Go  Giddy", "health": 90,
+                "body": [{"x": 9, "y": 1}, {"x": 9, "y": 2}, {"x": 9, "y": 3}],
+                "head": {"x": 9, "y": 1}, "length": 3, "shout": ""
+            }
+        }"#,
+        ));
+        let summary = || {
+            let mut summary = MoveSummary::new();
+            let outcomes = [(-1.0, 0.1, false), (0.5, 0.6, true), (0.8, 0.3, true)];
+            for (i, (h, p, alive)) in outcomes.iter().enumerate() {
+                summary.add(Outcome {
+                    board: board.clone(),
+                    h: *h,
+                    p: *p,
+                    alive: *alive,
+                    kills: vec![ALL_DIRECTIONS[i]],
+                });
+            }
+            summary
+        };
+        let (h, _, kills) = summary().resolve(SearchMode::Paranoid).unwrap();
+        assert_eq!(h, -1.0);
+        assert_eq!(kills, vec![Direction::Right]);
+        let (h, _, kills) = summary().resolve(SearchMode::Expectimax).unwrap();
+        assert!((h - 0.44).abs() < 1e-5);
+        // kill moves go along with the board the search continues from
+        assert_eq!(kills, vec![Direction::Left]);
+        let (h, _, _) = summary().resolve(SearchMode::Mixed(0.5)).unwrap();
+        assert!((h - -0.28).abs() < 1e-5);
+    }
+
+    #[test]
+    fn endgame() {
+        let board = Board::from_api(&GameInfo::new(
+            r#"{
+            "game": {"id": "game-1", "timeout": 500},
+            "turn": 5,
+            "board": {
+                "height": 11,
+                "width": 11,
+                "food": [],
+                "hazards": [],
+                "snakes": [
+                    {
+                        "id": "me", "name": "Go  Giddy", "health": 90,
+                        "body": [{"x": 9, "y": 1}, {"x": 9, "y": 2}, {"x": 9, "y": 3}],
+                        "head": {"x": 9, "y": 1}, "length": 3, "shout": ""
+                    }
+                ]
+            },
+            "you": {
+                "id": "me", "name": "Go  Giddy", "health": 90,
+                "body": [{"x": 9, "y": 1}, {"x": 9, "y": 2}, {"x": 9, "y": 3}],
+                "head": {"x": 9, "y": 1}, "length": 3, "shout": ""
+            }
+        }"#,
+        ));
+        // second row of the cycle runs right to left
+        assert_eq!(get_endgame_move(&board, &[], 15.0), Some(Direction::Left));
+        assert_eq!(get_endgame_move(&board, &[Point::new(0, 0)], 15.0), None);
+    }
+
+    #[test]
+    fn won_line() {
+        // going up is a pocket, but the other snake has to take it too and
+        // loses the head to head
+        let gameinfo = GameInfo::new(
+            r#"{
+            "game": {"id": "game-1", "timeout": 500},
+            "turn": 5,
+            "board": {
+                "height": 5,
+                "width": 5,
+                "food": [],
+                "hazards": [],
+                "snakes": [
+                    {"id": "me", "name": "me", "health": 90, "body": [
+                        {"x": 0, "y": 1}, {"x": 1, "y": 1}, {"x": 1, "y": 2}, {"x": 1, "y": 3}, {"x": 2, "y": 3}
+                    ], "head": {"x": 0, "y": 1}, "length": 5, "shout": ""},
+                    {"id": "other", "name": "other", "health": 90, "body": [
+                        {"x": 0, "y": 3}, {"x": 0, "y": 4}, {"x": 1, "y": 4}, {"x": 2, "y": 4}
+                    ], "head": {"x": 0, "y": 3}, "length": 4, "shout": ""}
+                ]
+            },
+            "you": {"id": "me", "name": "me", "health": 90, "body": [
+                {"x": 0, "y": 1}, {"x": 1, "y": 1}, {"x": 1, "y": 2}, {"x": 1, "y": 3}, {"x": 2, "y": 3}
+            ], "head": {"x": 0, "y": 1}, "length": 5, "shout": ""}
+        }"#,
+        );
+        let board = Board::from_api(&gameinfo);
+        assert!(!board.is_safe_move(0, Direction::Up));
+        let mut settings = Settings::default();
+        // going down boxes the other snake in only a turn later
+        settings.search.max_depth = Some(1);
+        let mut ctx = GameContext::new(&gameinfo, Default::default());
+        let evaluator = WeightedEvaluator::default();
+        let res = get_move(&gameinfo, &mut ctx, Duration::from_millis(200), &settings, &evaluator);
+        assert_eq!(res.movement, Direction::Up);
+        let scores = ctx.search.unwrap().root_scores;
+        assert_eq!(scores[Direction::Up.as_index()], Some(WIN_SCORE));
+    }
+
     #[test]
     fn test_test() {
         let data = GameInfo::new(
@@ -635,7 +1186,13 @@ mod test {
         "#,
         );
         // https://play.battlesnake.com/g/66a99167-b263-4c9f-988e-087f5df286be/?turn=0
-        let res = get_move(&data, Duration::from_millis(280));
+        let mut ctx = GameContext::new(&data, Default::default());
+        let evaluator = WeightedEvaluator::default();
+        let mut settings = Settings::default();
+        let res = get_move(&data, &mut ctx, Duration::from_millis(280), &settings, &evaluator);
+        dbg!(&res);
+        settings.search = "expectimax/softmax:0.5".parse::<SearchParams>().unwrap();
+        let res = get_move(&data, &mut ctx, Duration::from_millis(280), &settings, &evaluator);
         GameStateLog::from_api(&data).print();
         dbg!(res);
     }
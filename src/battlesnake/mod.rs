@@ -1,27 +1,48 @@
-pub use astar::Astar;
 pub use dfs::Dfs;
 pub use direction::{Direction, ALL_DIRECTIONS};
 pub use domove::Move;
+pub use evaluator::Evaluator;
+pub use game::GameRegistry;
 pub use game_logger::{init_logger, SessionStats};
 pub use grid::{GameGrid, GridObject};
-pub use heuristic::Heuristic;
 pub use input::GameInfo;
 pub use path::Path;
 pub use point::Point;
 pub use snake::{Snake, SnakeProps};
 pub use board::{Board, CauseOfDeath};
-pub use minimax::get_move;
 
-pub mod astar;
+pub mod arena;
 pub mod board;
+pub mod chokepoint;
+pub mod config;
+pub mod dataset;
 pub mod dfs;
 pub mod direction;
+pub mod distance;
+pub mod evaluator;
 pub mod domove;
+pub mod floodfill;
+pub mod foodrace;
+pub mod game;
 pub mod game_logger;
 pub mod grid;
-pub mod heuristic;
+pub mod hamilton;
 pub mod input;
+pub mod lethality;
 pub mod minimax;
+#[cfg(feature = "nn")]
+pub mod nn;
+pub mod opponent;
 pub mod path;
+pub mod pathfinding;
+pub mod planes;
 pub mod point;
+pub mod replay;
+pub mod safety;
 pub mod snake;
+pub mod spacefill;
+pub mod strategy;
+pub mod tactics;
+pub mod td;
+pub mod territory;
+pub mod tuner;
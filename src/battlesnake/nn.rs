@@ -0,0 +1,326 @@
+//! Neural network evaluator, built with the `nn` feature
+//!
+//! A small network over the board planes of `planes::encode`: 3x3 convolutions
+//! with zero padding followed by dense layers, ReLU after every layer but the
+//! last. The last layer outputs the value of the position as a logit, optionally
+//! followed by 4 policy logits indexed by `Direction::as_index`.
+//!
+//! Weights are read from a JSON file:
+//!
+//! ```json
+//! {
+//!   "width": 11, "height": 11,
+//!   "conv": [{"inputs": 8, "outputs": 16, "weights": [...], "bias": [...]}],
+//!   "dense": [{"inputs": 1936, "outputs": 5, "weights": [...], "bias": [...]}]
+//! }
+//! ```
+//!
+//! Convolution weights are laid out as `[output][input][ky][kx]`, dense weights
+//! as `[output][input]`. Dense input of the first layer is the flattened
+//! output of the convolutions, `[channel][y][x]`.
+//!
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::Arc;
+
+use super::evaluator::{WeightedEvaluator, WIN_SCORE};
+use super::planes::{self, PLANES};
+use super::Board;
+use super::Evaluator;
+use super::Point;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Layer {
+    pub inputs: usize,
+    pub outputs: usize,
+    pub weights: Vec<f32>,
+    pub bias: Vec<f32>,
+}
+
+impl Layer {
+    fn validate(&self, kernel: usize) -> Result<(), String> {
+        if self.weights.len() != self.outputs * self.inputs * kernel {
+            return Err(format!(
+                "Layer {}x{} has {} weights",
+                self.inputs,
+                self.outputs,
+                self.weights.len()
+            ));
+        }
+        if self.bias.len() != self.outputs {
+            return Err(format!(
+                "Layer {}x{} has {} biases",
+                self.inputs,
+                self.outputs,
+                self.bias.len()
+            ));
+        }
+        Ok(())
+    }
+
+    /// 3x3 convolution of `input` planes of `width * height`
+    fn convolve(&self, input: &[f32], width: usize, height: usize) -> Vec<f32> {
+        let area = width * height;
+        let mut output = vec![0.0; self.outputs * area];
+        for o in 0..self.outputs {
+            let out = &mut output[o * area..(o + 1) * area];
+            out.iter_mut().for_each(|v| *v = self.bias[o]);
+            for i in 0..self.inputs {
+                let plane = &input[i * area..(i + 1) * area];
+                let kernel = &self.weights[(o * self.inputs + i) * 9..][..9];
+                for y in 0..height {
+                    for x in 0..width {
+                        let mut sum = 0.0;
+                        for ky in 0..3 {
+                            let yy = y + ky;
+                            if yy < 1 || yy > height {
+                                continue;
+                            }
+                            for kx in 0..3 {
+                                let xx = x + kx;
+                                if xx < 1 || xx > width {
+                                    continue;
+                                }
+                                sum += kernel[ky * 3 + kx] * plane[(yy - 1) * width + xx - 1];
+                            }
+                        }
+                        out[y * width + x] += sum;
+                    }
+                }
+            }
+        }
+        output
+    }
+
+    fn multiply(&self, input: &[f32]) -> Vec<f32> {
+        self.weights
+            .chunks(self.inputs)
+            .zip(self.bias.iter())
+            .map(|(row, b)| {
+                b + row
+                    .iter()
+                    .zip(input.iter())
+                    .map(|(w, x)| w * x)
+                    .sum::<f32>()
+            })
+            .collect()
+    }
+}
+
+fn relu(values: &mut [f32]) {
+    values.iter_mut().for_each(|v| *v = v.max(0.0));
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Network {
+    /// board size the network was trained for
+    pub width: usize,
+    pub height: usize,
+    #[serde(default)]
+    pub conv: Vec<Layer>,
+    pub dense: Vec<Layer>,
+}
+
+impl Network {
+    pub fn from_json(data: &str) -> Result<Self, String> {
+        let network: Self =
+            serde_json::from_str(data).map_err(|e| format!("Invalid network: {}", e))?;
+        network.validate()?;
+        Ok(network)
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let data = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+        Self::from_json(&data).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Checks that the layers fit together
+    pub fn validate(&self) -> Result<(), String> {
+        let mut channels = PLANES;
+        for layer in self.conv.iter() {
+            layer.validate(9)?;
+            if layer.inputs != channels {
+                return Err(format!(
+                    "Convolution expects {} channels, got {}",
+                    layer.inputs, channels
+                ));
+            }
+            channels = layer.outputs;
+        }
+        let mut inputs = channels * self.width * self.height;
+        for layer in self.dense.iter() {
+            layer.validate(1)?;
+            if layer.inputs != inputs {
+                return Err(format!(
+                    "Dense layer expects {} inputs, got {}",
+                    layer.inputs, inputs
+                ));
+            }
+            inputs = layer.outputs;
+        }
+        match inputs {
+            _ if self.dense.is_empty() => Err("Network has no dense layers".to_string()),
+            1 | 5 => Ok(()),
+            n => Err(format!("Network has {} outputs, expected 1 or 5", n)),
+        }
+    }
+
+    /// Raw outputs for encoded planes of a board the network's size
+    pub fn forward(&self, planes: &[f32]) -> Vec<f32> {
+        let mut values = planes.to_vec();
+        for layer in self.conv.iter() {
+            values = layer.convolve(&values, self.width, self.height);
+            relu(&mut values);
+        }
+        for (i, layer) in self.dense.iter().enumerate() {
+            values = layer.multiply(&values);
+            if i + 1 < self.dense.len() {
+                relu(&mut values);
+            }
+        }
+        values
+    }
+
+    pub fn fits(&self, board: &Board) -> bool {
+        board.get_width() == self.width && board.get_height() == self.height
+    }
+
+    /// Value 0.0 - 1.0 and move probabilities of snake `index`,
+    /// probabilities are uniform for value only networks
+    pub fn predict(&self, board: &Board, index: usize, hazards: &[Point]) -> (f32, [f32; 4]) {
+        let output = self.forward(&planes::encode(board, index, hazards));
+        let mut policy = [0.25; 4];
+        if output.len() == 5 {
+            let max = output[1..].iter().cloned().fold(f32::MIN, f32::max);
+            let exp: Vec<f32> = output[1..].iter().map(|v| (v - max).exp()).collect();
+            let total: f32 = exp.iter().sum();
+            for (p, e) in policy.iter_mut().zip(exp.iter()) {
+                *p = e / total;
+            }
+        }
+        (sigmoid(output[0]), policy)
+    }
+}
+
+/// Scores boards by the network's value, boards of other sizes by `fallback`
+pub struct NnEvaluator {
+    network: Arc<Network>,
+    fallback: WeightedEvaluator,
+}
+
+impl NnEvaluator {
+    pub fn new(network: Arc<Network>, fallback: WeightedEvaluator) -> Self {
+        Self { network, fallback }
+    }
+}
+
+impl Evaluator for NnEvaluator {
+    fn evaluate(&self, board: &Board, index: usize, hazards: &[Point]) -> f32 {
+        if !self.network.fits(board) {
+            return self.fallback.evaluate(board, index, hazards);
+        }
+        if index == 0 && board.snakes.len() == 1 {
+            return WIN_SCORE;
+        }
+        self.network.predict(board, index, hazards).0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::GameInfo;
+    use super::*;
+
+    fn board(width: usize) -> Board {
+        Board::from_api(&GameInfo::new(&format!(
+            r#"{{
+            "game": {{"id": "game-1", "timeout": 500}},
+            "turn": 5,
+            "board": {{
+                "height": 3,
+                "width": {},
+                "food": [{{"x": 2, "y": 2}}],
+                "hazards": [],
+                "snakes": [
+                    {{"id": "me", "name": "me", "health": 50, "body": [{{"x": 0, "y": 0}}, {{"x": 1, "y": 0}}], "head": {{"x": 0, "y": 0}}, "length": 2, "shout": ""}},
+                    {{"id": "other", "name": "other", "health": 90, "body": [{{"x": 2, "y": 1}}, {{"x": 2, "y": 0}}], "head": {{"x": 2, "y": 1}}, "length": 2, "shout": ""}}
+                ]
+            }},
+            "you": {{"id": "me", "name": "me", "health": 50, "body": [{{"x": 0, "y": 0}}, {{"x": 1, "y": 0}}], "head": {{"x": 0, "y": 0}}, "length": 2, "shout": ""}}
+        }}"#,
+            width
+        )))
+    }
+
+    /// Single convolution summing the food plane around each cell,
+    /// value is the sum over cells, policy prefers the second move
+    fn network() -> Network {
+        let mut conv = vec![0.0; PLANES * 9];
+        conv[5 * 9..6 * 9].iter_mut().for_each(|w| *w = 1.0);
+        let mut dense = vec![1.0; 9];
+        dense.extend(vec![0.0; 9 * 4]);
+        dense[9 * 2 + 8] = 1.0;
+        Network {
+            width: 3,
+            height: 3,
+            conv: vec![Layer {
+                inputs: PLANES,
+                outputs: 1,
+                weights: conv,
+                bias: vec![0.0],
+            }],
+            dense: vec![Layer {
+                inputs: 9,
+                outputs: 5,
+                weights: dense,
+                bias: vec![-4.0, 0.0, 0.0, 0.0, 0.0],
+            }],
+        }
+    }
+
+    #[test]
+    fn forward() {
+        let network = network();
+        assert!(network.validate().is_ok());
+        let b = board(3);
+        let planes = planes::encode(&b, 0, &[]);
+        // food in the corner reaches 4 cells through the 3x3 kernel
+        let output = network.forward(&planes);
+        assert_eq!(output, vec![0.0, 0.0, 1.0, 0.0, 0.0]);
+        let (value, policy) = network.predict(&b, 0, &[]);
+        assert_eq!(value, 0.5);
+        assert!(policy[1] > policy[0] && policy[1] > policy[2]);
+        assert!((policy.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+
+        let evaluator = NnEvaluator::new(Arc::new(network), WeightedEvaluator::default());
+        assert_eq!(evaluator.evaluate(&b, 0, &[]), 0.5);
+        let wide = board(5);
+        assert_eq!(
+            evaluator.evaluate(&wide, 0, &[]),
+            WeightedEvaluator::default().evaluate(&wide, 0, &[])
+        );
+    }
+
+    #[test]
+    fn load() {
+        let json = serde_json::to_string(&network()).unwrap();
+        let network = Network::from_json(&json).unwrap();
+        assert_eq!(network.conv[0].weights.len(), PLANES * 9);
+        let mut broken = network.clone();
+        broken.dense[0].inputs = 8;
+        assert!(broken.validate().is_err());
+        let mut broken = network.clone();
+        broken.conv[0].bias.clear();
+        assert!(broken.validate().is_err());
+        let mut broken = network;
+        broken.dense[0].outputs = 4;
+        assert!(broken.validate().is_err());
+        assert!(Network::from_json(r#"{"width": 3, "height": 3, "dense": []}"#).is_err());
+    }
+}
@@ -0,0 +1,293 @@
+//! Opponent modelling from observed move history
+//!
+//! Every observed opponent move is described by a few behavioural traits
+//! (did it move towards food, did it stay away from heads of snakes that would
+//! win a head to head, did it move towards its own tail). A trait is only
+//! counted when the snake actually had a choice, i.e. some of its safe moves
+//! had the trait and some did not. The learned rates are then used to estimate
+//! probability of each move the opponent can make.
+//!
+use super::Board;
+use super::Direction;
+
+/// Opponent moves less likely than this are dropped from the search
+const PRUNE_PROBABILITY: f32 = 0.05;
+/// Minimal number of observed moves before the model is trusted for pruning
+const PRUNE_MIN_OBSERVED: u32 = 10;
+
+/// Laplace smoothed counter of how often a trait was chosen when possible
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Trait {
+    pub hits: u32,
+    pub chances: u32,
+}
+
+impl Trait {
+    pub fn rate(&self) -> f32 {
+        (self.hits + 1) as f32 / (self.chances + 2) as f32
+    }
+
+    fn record(&mut self, chosen: bool) {
+        self.chances += 1;
+        if chosen {
+            self.hits += 1;
+        }
+    }
+
+    fn merge(&mut self, other: &Trait) {
+        self.hits += other.hits;
+        self.chances += other.chances;
+    }
+}
+
+/// Traits of a single move
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MoveTraits {
+    pub food_seeking: bool,
+    pub head_avoiding: bool,
+    pub tail_chasing: bool,
+}
+
+impl MoveTraits {
+    pub fn new(board: &Board, index: usize, dir: Direction) -> Self {
+        let snake = &board.snakes[index];
+        let head = snake.head();
+        let next = head + dir;
+        let closest_food =
+            |p: &super::Point| board.food.iter().map(|f| f.manhattan_distance(p)).min();
+        let food_seeking = match (closest_food(&head), closest_food(&next)) {
+            (Some(now), Some(then)) => then < now,
+            _ => false,
+        };
+        let head_avoiding = board.snakes.iter().enumerate().all(|(i, other)| {
+            i == index || other.size() < snake.size() || !next.is_neighbour(other.head())
+        });
+        let tail = snake.body.last().unwrap();
+        let tail_chasing = next.manhattan_distance(&tail) < head.manhattan_distance(&tail);
+        Self {
+            food_seeking,
+            head_avoiding,
+            tail_chasing,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BehaviourProfile {
+    pub food_seeking: Trait,
+    pub head_avoiding: Trait,
+    pub tail_chasing: Trait,
+    pub observed: u32,
+}
+
+impl BehaviourProfile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records move `dir` made by snake `index` on `board` (the board before the move)
+    pub fn observe(&mut self, board: &Board, index: usize, dir: Direction) {
+        let options: Vec<MoveTraits> = board
+            .get_pruned_moves(&board.snakes[index].head(), 1)
+            .iter()
+            .map(|d| MoveTraits::new(board, index, *d))
+            .collect();
+        let chosen = MoveTraits::new(board, index, dir);
+        self.observed += 1;
+        if options.iter().any(|t| t.food_seeking) && options.iter().any(|t| !t.food_seeking) {
+            self.food_seeking.record(chosen.food_seeking);
+        }
+        if options.iter().any(|t| t.head_avoiding) && options.iter().any(|t| !t.head_avoiding) {
+            self.head_avoiding.record(chosen.head_avoiding);
+        }
+        if options.iter().any(|t| t.tail_chasing) && options.iter().any(|t| !t.tail_chasing) {
+            self.tail_chasing.record(chosen.tail_chasing);
+        }
+    }
+
+    pub fn merge(&mut self, other: &BehaviourProfile) {
+        self.food_seeking.merge(&other.food_seeking);
+        self.head_avoiding.merge(&other.head_avoiding);
+        self.tail_chasing.merge(&other.tail_chasing);
+        self.observed += other.observed;
+    }
+
+    /// Probability of each of `moves`, sums up to 1.0
+    pub fn probabilities(&self, board: &Board, index: usize, moves: &[Direction]) -> Vec<f32> {
+        let weight = |t: &Trait, has: bool| if has { t.rate() } else { 1.0 - t.rate() };
+        let scores: Vec<f32> = moves
+            .iter()
+            .map(|dir| {
+                let traits = MoveTraits::new(board, index, *dir);
+                weight(&self.food_seeking, traits.food_seeking)
+                    * weight(&self.head_avoiding, traits.head_avoiding)
+                    * weight(&self.tail_chasing, traits.tail_chasing)
+            })
+            .collect();
+        let total: f32 = scores.iter().sum();
+        if total <= 0.0 {
+            return vec![1.0 / moves.len() as f32; moves.len()];
+        }
+        scores.iter().map(|s| s / total).collect()
+    }
+}
+
+/// Softmax over a quick evaluation of each move: free cells around the target,
+/// staying away from heads of bigger snakes and getting closer to food.
+/// Lower `temperature` makes the distribution greedier.
+pub fn softmax_probabilities(
+    board: &Board,
+    index: usize,
+    moves: &[Direction],
+    temperature: f32,
+) -> Vec<f32> {
+    let head = board.snakes[index].head();
+    let scores: Vec<f32> = moves
+        .iter()
+        .map(|dir| {
+            let traits = MoveTraits::new(board, index, *dir);
+            let space = board.get_pruned_moves(&(head + dir), 1).len() as f32;
+            space + traits.food_seeking as u8 as f32 + 2.0 * traits.head_avoiding as u8 as f32
+        })
+        .collect();
+    let max = scores.iter().cloned().fold(f32::MIN, f32::max);
+    let exps: Vec<f32> = scores
+        .iter()
+        .map(|s| ((s - max) / temperature.max(0.01)).exp())
+        .collect();
+    let total: f32 = exps.iter().sum();
+    exps.iter().map(|e| e / total).collect()
+}
+
+/// Behaviour profiles of all snakes on the root board, indexed by `Snake::id`
+#[derive(Debug, Clone, Default)]
+pub struct OpponentModel {
+    profiles: Vec<Option<BehaviourProfile>>,
+}
+
+impl OpponentModel {
+    pub fn new(profiles: Vec<Option<BehaviourProfile>>) -> Self {
+        Self { profiles }
+    }
+
+    fn get_profile(&self, board: &Board, index: usize) -> Option<&BehaviourProfile> {
+        self.profiles
+            .get(board.snakes[index].id)
+            .and_then(|p| p.as_ref())
+    }
+
+    /// Probability of each move, uniform for snakes without a profile
+    pub fn move_probabilities(&self, board: &Board, index: usize, moves: &[Direction]) -> Vec<f32> {
+        match self.get_profile(board, index) {
+            Some(profile) => profile.probabilities(board, index, moves),
+            None => vec![1.0 / moves.len() as f32; moves.len()],
+        }
+    }
+
+    /// Orders moves from the most likely and drops the unlikely ones
+    /// once the opponent was observed long enough. Never returns empty moves.
+    pub fn order_moves(&self, board: &Board, index: usize, moves: &[Direction]) -> Vec<Direction> {
+        let mut ranked: Vec<(Direction, f32)> = moves
+            .iter()
+            .cloned()
+            .zip(self.move_probabilities(board, index, moves))
+            .collect();
+        ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        let confident = matches!(
+            self.get_profile(board, index),
+            Some(p) if p.observed >= PRUNE_MIN_OBSERVED
+        );
+        ranked
+            .iter()
+            .enumerate()
+            .filter(|(i, (_, p))| *i == 0 || !confident || *p >= PRUNE_PROBABILITY)
+            .map(|(_, (dir, _))| *dir)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::GameInfo;
+    use super::*;
+
+    fn board() -> Board {
+        // enemy at {5, 5} moving up, food up-left from it, we are far away
+        Board::from_api(&GameInfo::new(
+            r#"{
+            "game": {"id": "game-1", "timeout": 500},
+            "turn": 5,
+            "board": {
+                "height": 11,
+                "width": 11,
+                "food": [{"x": 3, "y": 9}],
+                "hazards": [],
+                "snakes": [
+                    {
+                        "id": "me", "name": "Go  Giddy", "health": 90,
+                        "body": [{"x": 9, "y": 1}, {"x": 9, "y": 2}, {"x": 9, "y": 3}],
+                        "head": {"x": 9, "y": 1}, "length": 3, "shout": ""
+                    },
+                    {
+                        "id": "enemy", "name": "Other", "health": 90,
+                        "body": [{"x": 5, "y": 5}, {"x": 5, "y": 4}, {"x": 5, "y": 3}],
+                        "head": {"x": 5, "y": 5}, "length": 3, "shout": ""
+                    }
+                ]
+            },
+            "you": {
+                "id": "me", "name": "Go  Giddy", "health": 90,
+                "body": [{"x": 9, "y": 1}, {"x": 9, "y": 2}, {"x": 9, "y": 3}],
+                "head": {"x": 9, "y": 1}, "length": 3, "shout": ""
+            }
+        }"#,
+        ))
+    }
+
+    #[test]
+    fn traits() {
+        let board = board();
+        let up = MoveTraits::new(&board, 1, Direction::Up);
+        assert!(up.food_seeking);
+        assert!(up.head_avoiding);
+        assert!(!up.tail_chasing);
+        let right = MoveTraits::new(&board, 1, Direction::Right);
+        assert!(!right.food_seeking);
+    }
+
+    #[test]
+    fn softmax() {
+        let board = board();
+        let moves = board.get_pruned_moves(&board.snakes[1].head(), 1);
+        let probs = softmax_probabilities(&board, 1, &moves, 1.0);
+        assert!((probs.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+        // right is the only move not getting closer to food
+        let right = moves.iter().position(|d| *d == Direction::Right).unwrap();
+        assert!(probs.iter().all(|p| *p >= probs[right]));
+        let greedy = softmax_probabilities(&board, 1, &moves, 0.1);
+        assert!(greedy[right] < probs[right]);
+    }
+
+    #[test]
+    fn learn_food_seeking() {
+        let board = board();
+        let moves = board.get_pruned_moves(&board.snakes[1].head(), 1);
+        let mut profile = BehaviourProfile::new();
+        let uniform = profile.probabilities(&board, 1, &moves);
+        assert!(uniform
+            .iter()
+            .all(|p| (p - uniform[0]).abs() < f32::EPSILON));
+        for _ in 0..20 {
+            profile.observe(&board, 1, Direction::Up);
+        }
+        assert_eq!(profile.food_seeking.chances, 20);
+        assert_eq!(profile.food_seeking.hits, 20);
+        let model = OpponentModel::new(vec![None, Some(profile)]);
+        // up and left both get closer to the food, right is pruned
+        let ordered = model.order_moves(&board, 1, &moves);
+        assert_eq!(ordered, vec![Direction::Left, Direction::Up]);
+        // snakes without profile keep all moves
+        let ours = board.get_pruned_moves(&board.snakes[0].head(), 1);
+        assert_eq!(model.order_moves(&board, 0, &ours).len(), ours.len());
+    }
+}
@@ -0,0 +1,535 @@
+//! Generic A* and Dijkstra search over a grid of points
+//!
+//! The searches only know about points and their 4 neighbours, everything
+//! else comes from a `Cost`: which cells can be entered, how much a step
+//! costs and the estimate of the remaining cost. Steps know the turn they
+//! are made on and the food eaten on the way there, so costs can account for
+//! cells that become free later, like the tail end of a snake. `Occupancy`
+//! tracks when body cells are vacated for the costs of this module.
+//!
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use super::grid::{GameGrid, GridObject};
+use super::Board;
+use super::Path;
+use super::Point;
+use super::ALL_DIRECTIONS;
+
+/// When a step is made
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Time {
+    /// moves since the start, the first step is made on turn 1
+    pub turn: usize,
+    /// food eaten on the way before this step
+    pub eaten: usize,
+}
+
+pub trait Cost {
+    /// Cost of stepping from `from` to its neighbour `to` at `time`.
+    /// None if `to` cannot be entered then.
+    fn step(&self, from: &Point, to: &Point, time: Time) -> Option<usize>;
+
+    /// Estimate of the cost from `p` to `goal`, the default zero makes A* a Dijkstra search
+    fn estimate(&self, _p: &Point, _goal: &Point) -> usize {
+        0
+    }
+
+    /// Whether stepping on `p` eats food
+    fn eats(&self, _p: &Point) -> bool {
+        false
+    }
+}
+
+/// Turns until cells covered by snakes are vacated.
+///
+/// Segment `i` of a snake of length `s`, head being 0, is gone after `s - i`
+/// moves. Every food the snake eats keeps the tail in place for one more
+/// turn. The growth of the moving snake follows its path, other snakes are
+/// expected to eat if a food is closer to their head than the segment's turn.
+#[derive(Debug, Clone)]
+pub struct Occupancy {
+    width: usize,
+    height: usize,
+    /// owner and base turn of vacating per cell
+    cells: Vec<Option<(usize, usize)>>,
+    /// distance of each snake's head to the closest food
+    food_distance: Vec<usize>,
+    food: Vec<Point>,
+}
+
+impl Occupancy {
+    /// `bodies` head first
+    pub fn new(width: usize, height: usize, bodies: &[Vec<Point>], food: &[Point]) -> Self {
+        let mut cells = vec![None; width * height];
+        for (owner, body) in bodies.iter().enumerate() {
+            let size = body.len();
+            for (i, p) in body.iter().enumerate() {
+                if p.x < 0 || p.y < 0 || p.x as usize >= width || p.y as usize >= height {
+                    continue;
+                }
+                let cell = &mut cells[p.y as usize * width + p.x as usize];
+                // stacked segments leave with the last one
+                match cell {
+                    Some((_, turn)) if *turn >= size - i => (),
+                    _ => *cell = Some((owner, size - i)),
+                }
+            }
+        }
+        let food_distance = bodies
+            .iter()
+            .map(|body| match body.first() {
+                Some(head) => food
+                    .iter()
+                    .map(|f| f.manhattan_distance(head))
+                    .min()
+                    .unwrap_or(usize::MAX),
+                None => usize::MAX,
+            })
+            .collect();
+        Self {
+            width,
+            height,
+            cells,
+            food_distance,
+            food: food.to_vec(),
+        }
+    }
+
+    pub fn from_board(board: &Board) -> Self {
+        let bodies: Vec<Vec<Point>> = board.snakes.iter().map(|s| s.body.nodes.clone()).collect();
+        Self::new(board.get_width(), board.get_height(), &bodies, &board.food)
+    }
+
+    fn is_inbounds(&self, p: &Point) -> bool {
+        p.x >= 0 && p.y >= 0 && (p.x as usize) < self.width && (p.y as usize) < self.height
+    }
+
+    /// Turn from which `p` can be entered by snake `mover` at `time`, 0 for free cells
+    pub fn get_free_turn(&self, p: &Point, mover: Option<usize>, eaten: usize) -> usize {
+        if !self.is_inbounds(p) {
+            return 0;
+        }
+        match self.cells[p.y as usize * self.width + p.x as usize] {
+            None => 0,
+            Some((owner, turn)) if Some(owner) == mover => turn + eaten,
+            Some((owner, turn)) if self.food_distance[owner] < turn => turn + 1,
+            Some((_, turn)) => turn,
+        }
+    }
+
+    /// Whether inbound `p` can be entered by snake `mover` at `time`
+    pub fn is_free(&self, p: &Point, mover: Option<usize>, time: Time) -> bool {
+        self.is_inbounds(p) && time.turn >= self.get_free_turn(p, mover, time.eaten)
+    }
+
+    pub fn is_food(&self, p: &Point) -> bool {
+        self.food.contains(p)
+    }
+}
+
+/// Queue node (point to explore, f_score)
+#[derive(Eq)]
+struct OpenNode(Point, usize);
+
+impl Ord for OpenNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.1.cmp(&other.1).reverse() //we want a min heap
+    }
+}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for OpenNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.1 == other.1
+    }
+}
+
+/// Cheapest path from `start` to `goal` and its cost.
+/// Path nodes go from `goal` back to `start`, both included.
+pub fn astar<C: Cost + ?Sized>(cost: &C, start: Point, goal: Point) -> Option<(usize, Path)> {
+    let mut openset: BinaryHeap<OpenNode> = BinaryHeap::new();
+    // keeps min cost per point <point, (g_cost, time of arrival, parent)>
+    let mut closedset: HashMap<Point, (usize, Time, Option<Point>)> = HashMap::new();
+
+    openset.push(OpenNode(start, cost.estimate(&start, &goal)));
+    closedset.insert(start, (0, Time { turn: 0, eaten: 0 }, None));
+
+    while let Some(OpenNode(point, _)) = openset.pop() {
+        let (g_score, time) = next_step(&closedset[&point], cost, &point);
+        if point == goal {
+            let mut nodes = vec![point];
+            while let Some((_, _, Some(parent))) = closedset.get(nodes.last().unwrap()) {
+                nodes.push(*parent);
+            }
+            return Some((g_score, Path::from_vec(nodes)));
+        }
+
+        for dir in ALL_DIRECTIONS.iter() {
+            let n = point + dir;
+            let new_g_score = match cost.step(&point, &n, time) {
+                Some(step) => g_score + step,
+                None => continue,
+            };
+            if matches!(closedset.get(&n), Some((old, ..)) if *old <= new_g_score) {
+                continue;
+            }
+            closedset.insert(n, (new_g_score, time, Some(point)));
+            openset.push(OpenNode(n, new_g_score + cost.estimate(&n, &goal)));
+        }
+    }
+    None
+}
+
+/// Cost so far and time of the next step from a closed `point`
+fn next_step<C: Cost + ?Sized>(
+    closed: &(usize, Time, Option<Point>),
+    cost: &C,
+    point: &Point,
+) -> (usize, Time) {
+    let (g_score, time, parent) = closed;
+    let eaten = if parent.is_some() && cost.eats(point) {
+        time.eaten + 1
+    } else {
+        time.eaten
+    };
+    (
+        *g_score,
+        Time {
+            turn: time.turn + 1,
+            eaten,
+        },
+    )
+}
+
+/// Cost of the cheapest path from any of `sources` to every reachable point and
+/// the time it arrives there
+pub fn dijkstra<C: Cost + ?Sized>(cost: &C, sources: &[Point]) -> HashMap<Point, (usize, Time)> {
+    let mut openset: BinaryHeap<OpenNode> = BinaryHeap::new();
+    // <point, (g_cost, time of arrival, parent)>
+    let mut closedset: HashMap<Point, (usize, Time, Option<Point>)> = HashMap::new();
+    for source in sources.iter() {
+        openset.push(OpenNode(*source, 0));
+        closedset.insert(*source, (0, Time { turn: 0, eaten: 0 }, None));
+    }
+
+    while let Some(OpenNode(point, g_score)) = openset.pop() {
+        let closed = &closedset[&point];
+        if closed.0 < g_score {
+            continue;
+        }
+        let (_, time) = next_step(closed, cost, &point);
+        for dir in ALL_DIRECTIONS.iter() {
+            let n = point + dir;
+            let new_g_score = match cost.step(&point, &n, time) {
+                Some(step) => g_score + step,
+                None => continue,
+            };
+            if matches!(closedset.get(&n), Some((old, ..)) if *old <= new_g_score) {
+                continue;
+            }
+            closedset.insert(n, (new_g_score, time, Some(point)));
+            openset.push(OpenNode(n, new_g_score));
+        }
+    }
+    closedset
+        .into_iter()
+        .map(|(p, (g, time, _))| (p, (g, time)))
+        .collect()
+}
+
+/// Moves of snake `mover` on a `Board` avoiding snakes' bodies that are still
+/// there on the turn of the move, stepping on a hazard costs `hazard_cost` instead of 1
+pub struct BoardCost<'a> {
+    occupancy: Occupancy,
+    mover: Option<usize>,
+    hazards: &'a [Point],
+    hazard_cost: usize,
+}
+
+impl<'a> BoardCost<'a> {
+    /// Paths of no snake in particular if `mover` is none
+    pub fn new(
+        board: &Board,
+        mover: Option<usize>,
+        hazards: &'a [Point],
+        hazard_cost: usize,
+    ) -> Self {
+        Self {
+            occupancy: Occupancy::from_board(board),
+            mover,
+            hazards,
+            hazard_cost,
+        }
+    }
+}
+
+impl<'a> Cost for BoardCost<'a> {
+    fn step(&self, _from: &Point, to: &Point, time: Time) -> Option<usize> {
+        if !self.occupancy.is_free(to, self.mover, time) {
+            None
+        } else if self.hazards.contains(to) {
+            Some(self.hazard_cost)
+        } else {
+            Some(1)
+        }
+    }
+
+    /// Every step costs at least 1, or `hazard_cost` if that is less
+    fn estimate(&self, p: &Point, goal: &Point) -> usize {
+        p.manhattan_distance(goal) * self.hazard_cost.min(1)
+    }
+
+    fn eats(&self, p: &Point) -> bool {
+        self.occupancy.is_food(p)
+    }
+}
+
+/// Moves on accessible `GameGrid` cells, hazards cost `hazard_cost` and cells
+/// next to enemy heads their collision chance. Snake cells are walls
+/// unless an `Occupancy` tells when they are vacated.
+pub struct GridCost<'a> {
+    grid: &'a GameGrid,
+    occupancy: Option<(&'a Occupancy, usize)>,
+    hazard_cost: usize,
+}
+
+impl<'a> GridCost<'a> {
+    pub fn new(grid: &'a GameGrid, hazard_cost: usize) -> Self {
+        Self {
+            grid,
+            occupancy: None,
+            hazard_cost,
+        }
+    }
+
+    /// Lets snake `mover` pass snake cells once they are vacated
+    pub fn with_occupancy(mut self, occupancy: &'a Occupancy, mover: usize) -> Self {
+        self.occupancy = Some((occupancy, mover));
+        self
+    }
+}
+
+impl<'a> Cost for GridCost<'a> {
+    fn step(&self, _from: &Point, to: &Point, time: Time) -> Option<usize> {
+        match self.grid.get_value(to) {
+            GridObject::Snake(_) => match self.occupancy {
+                Some((occupancy, mover)) if occupancy.is_free(to, Some(mover), time) => Some(1),
+                _ => None,
+            },
+            GridObject::Outofbounds => None,
+            GridObject::Collisionchance(v) => Some(v.max(1) as usize),
+            GridObject::Hazard => Some(self.hazard_cost),
+            _ => Some(1),
+        }
+    }
+
+    /// No step costs less than 1
+    fn estimate(&self, p: &Point, goal: &Point) -> usize {
+        p.manhattan_distance(goal)
+    }
+
+    fn eats(&self, p: &Point) -> bool {
+        matches!(self.occupancy, Some((occupancy, _)) if occupancy.is_food(p))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::board::HAZARD_COST;
+    use super::super::GameInfo;
+    use super::*;
+
+    /// Every cell is free and costs 1, except `walls`
+    struct Walls(Vec<Point>);
+
+    impl Cost for Walls {
+        fn step(&self, _from: &Point, to: &Point, _time: Time) -> Option<usize> {
+            let inbounds = to.x >= 0 && to.y >= 0 && to.x < 5 && to.y < 5;
+            if inbounds && !self.0.contains(to) {
+                Some(1)
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Free 5x5 grid where `goal` can be entered only after eating at `food`
+    struct Hungry {
+        food: Point,
+        goal: Point,
+    }
+
+    impl Cost for Hungry {
+        fn step(&self, _from: &Point, to: &Point, time: Time) -> Option<usize> {
+            let inbounds = to.x >= 0 && to.y >= 0 && to.x < 5 && to.y < 5;
+            if inbounds && (*to != self.goal || time.eaten > 0) {
+                Some(1)
+            } else {
+                None
+            }
+        }
+
+        fn eats(&self, p: &Point) -> bool {
+            *p == self.food
+        }
+    }
+
+    #[test]
+    fn grid_manhattan() {
+        let start = Point::new(0, 0);
+        let end = Point::new(9, 9);
+        let grid = GameGrid::new((10, 10));
+        let (cost, path) = astar(&GridCost::new(&grid, HAZARD_COST), start, end).unwrap();
+        assert_eq!(cost, 18);
+        assert_eq!(path.nodes.len(), 19);
+        assert_eq!(path.first(), Some(end));
+        assert_eq!(path.last(), Some(start));
+    }
+
+    #[test]
+    fn grid_hazards() {
+        let start = Point::new(0, 0);
+        let end = Point::new(9, 9);
+        let mut grid = GameGrid::new((10, 10));
+        let hazards = vec![Point::new(1, 1)];
+        grid.set_hazards(&hazards);
+        let (cost, path) = astar(&GridCost::new(&grid, HAZARD_COST), start, end).unwrap();
+        assert_eq!(cost, 18);
+        assert!(!path.nodes.contains(&Point::new(1, 1)));
+        let (cost, _) = astar(&GridCost::new(&grid, 10), start, Point::new(1, 1)).unwrap();
+        assert_eq!(cost, 11);
+    }
+
+    #[test]
+    fn walls() {
+        // wall with a gap at the top
+        let walls = Walls((0..4).map(|y| Point::new(2, y)).collect());
+        let (cost, path) = astar(&walls, Point::new(0, 0), Point::new(4, 0)).unwrap();
+        assert_eq!(cost, 12);
+        assert!(path.nodes.contains(&Point::new(2, 4)));
+        let distances = dijkstra(&walls, &[Point::new(0, 0), Point::new(4, 4)]);
+        assert_eq!(distances[&Point::new(4, 0)].0, 4);
+        assert_eq!(distances[&Point::new(4, 0)].1.turn, 4);
+        assert_eq!(distances[&Point::new(1, 3)].0, 4);
+        assert_eq!(distances.len(), 21);
+        assert!(astar(
+            &Walls((0..5).map(|y| Point::new(2, y)).collect()),
+            Point::zero(),
+            Point::new(4, 0)
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn eating() {
+        let hungry = Hungry {
+            food: Point::new(2, 0),
+            goal: Point::new(4, 0),
+        };
+        let (cost, path) = astar(&hungry, Point::zero(), hungry.goal).unwrap();
+        assert_eq!(cost, 4);
+        assert!(path.nodes.contains(&hungry.food));
+        assert_eq!(dijkstra(&hungry, &[Point::zero()])[&hungry.goal].0, 4);
+        let starving = Hungry {
+            food: Point::new(4, 4),
+            goal: Point::new(4, 0),
+        };
+        assert!(!dijkstra(&starving, &[Point::new(4, 1)]).contains_key(&starving.goal));
+    }
+
+    #[test]
+    fn occupancy() {
+        let bodies = vec![
+            vec![
+                Point::new(0, 0),
+                Point::new(0, 1),
+                Point::new(0, 2),
+                Point::new(0, 2),
+            ],
+            vec![Point::new(4, 4), Point::new(4, 3), Point::new(4, 2)],
+        ];
+        let food = vec![Point::new(3, 4)];
+        let occupancy = Occupancy::new(5, 5, &bodies, &food);
+        // stacked tail of a snake that just ate leaves after 2 moves
+        assert_eq!(occupancy.get_free_turn(&Point::new(0, 2), Some(0), 0), 2);
+        assert_eq!(occupancy.get_free_turn(&Point::new(0, 1), Some(0), 0), 3);
+        // every food eaten on the way keeps our body a turn longer
+        assert_eq!(occupancy.get_free_turn(&Point::new(0, 1), Some(0), 2), 5);
+        // the other snake is next to food and may grow before its neck moves,
+        // its tail moves on the turn of eating
+        assert_eq!(occupancy.get_free_turn(&Point::new(4, 3), Some(0), 0), 3);
+        assert_eq!(occupancy.get_free_turn(&Point::new(4, 3), Some(1), 0), 2);
+        assert_eq!(occupancy.get_free_turn(&Point::new(4, 2), Some(0), 0), 1);
+        assert_eq!(occupancy.get_free_turn(&Point::new(2, 2), Some(0), 0), 0);
+        assert!(!occupancy.is_free(&Point::new(5, 0), None, Time { turn: 9, eaten: 0 }));
+        assert!(occupancy.is_food(&Point::new(3, 4)));
+    }
+
+    #[test]
+    fn grid_occupancy() {
+        // wall of a snake body with its tail at the bottom
+        let body: Vec<Point> = (0..5).rev().map(|y| Point::new(2, y)).collect();
+        let mut grid = GameGrid::new((5, 5));
+        grid.set_snakes(vec![body.clone(), vec![Point::new(0, 0)]]);
+        assert!(astar(
+            &GridCost::new(&grid, HAZARD_COST),
+            Point::zero(),
+            Point::new(4, 0)
+        )
+        .is_none());
+        let occupancy = Occupancy::new(5, 5, &[body, vec![Point::zero()]], &[]);
+        let cost = GridCost::new(&grid, HAZARD_COST).with_occupancy(&occupancy, 1);
+        // the tail at (2, 0) is gone by the time we get there
+        let (g, path) = astar(&cost, Point::zero(), Point::new(4, 0)).unwrap();
+        assert_eq!(g, 4);
+        assert_eq!(path.nodes.len(), 5);
+    }
+
+    #[test]
+    fn time_aware() {
+        let board = Board::from_api(&GameInfo::new(
+            r#"{
+            "game": {"id": "game-1", "timeout": 500},
+            "turn": 5,
+            "board": {
+                "height": 3,
+                "width": 3,
+                "food": [],
+                "hazards": [],
+                "snakes": [
+                    {"id": "me", "name": "me", "health": 50, "body": [{"x": 0, "y": 0}, {"x": 0, "y": 1}], "head": {"x": 0, "y": 0}, "length": 2, "shout": ""},
+                    {"id": "other", "name": "other", "health": 90, "body": [{"x": 2, "y": 2}, {"x": 1, "y": 2}, {"x": 1, "y": 1}, {"x": 1, "y": 0}], "head": {"x": 2, "y": 2}, "length": 4, "shout": ""}
+                ]
+            },
+            "you": {"id": "me", "name": "me", "health": 50, "body": [{"x": 0, "y": 0}, {"x": 0, "y": 1}], "head": {"x": 0, "y": 0}, "length": 2, "shout": ""}
+        }"#,
+        ));
+        let cost = BoardCost::new(&board, Some(0), &[], 1);
+        let at = |turn| Time { turn, eaten: 0 };
+        // the other snake's tail at (1, 0) is gone after the first move
+        assert_eq!(
+            cost.step(&Point::new(0, 0), &Point::new(1, 0), at(1)),
+            Some(1)
+        );
+        // its middle at (1, 1) stays for 2 turns
+        assert_eq!(cost.step(&Point::new(0, 1), &Point::new(1, 1), at(1)), None);
+        assert_eq!(
+            cost.step(&Point::new(0, 1), &Point::new(1, 1), at(2)),
+            Some(1)
+        );
+        let (g, path) = astar(&cost, Point::new(0, 0), Point::new(2, 0)).unwrap();
+        assert_eq!(g, 2);
+        assert_eq!(
+            path.nodes,
+            vec![Point::new(2, 0), Point::new(1, 0), Point::new(0, 0)]
+        );
+        let distances = dijkstra(&cost, &[Point::new(0, 0)]);
+        assert_eq!(distances[&Point::new(2, 1)].0, 3);
+    }
+}
@@ -0,0 +1,102 @@
+//! Board encoding as input planes for learned evaluators
+//!
+//! The board is encoded from the point of view of one snake as `PLANES` planes
+//! of `height * width` values, row-major with `y = 0` first:
+//!
+//! | plane | content |
+//! |-------|---------|
+//! | 0 | our body, 1.0 at the tail end fading to 1 / length at the head |
+//! | 1 | our head |
+//! | 2 | opponents' bodies, same fading as ours |
+//! | 3 | heads of opponents at least as long as us |
+//! | 4 | heads of shorter opponents |
+//! | 5 | food |
+//! | 6 | hazards |
+//! | 7 | our health / 100 on every cell |
+//!
+//! The fading tells how long the segment stays, cells vacated sooner have lower values.
+//!
+use super::Board;
+use super::Point;
+
+pub const PLANES: usize = 8;
+
+/// Encodes `board` for snake `index` into `PLANES * height * width` values
+pub fn encode(board: &Board, index: usize, hazards: &[Point]) -> Vec<f32> {
+    let area = board.get_width() * board.get_height();
+    let mut planes = vec![0.0; PLANES * area];
+    let mut set = |plane: usize, p: &Point, value: f32| {
+        if board.is_inbounds(p) {
+            let i = plane * area + board.get_index(p);
+            planes[i] = f32::max(planes[i], value);
+        }
+    };
+
+    let me = &board.snakes[index];
+    for (i, snake) in board.snakes.iter().enumerate() {
+        let size = snake.size() as f32;
+        let body_plane = if i == index { 0 } else { 2 };
+        for (j, p) in snake.body.nodes.iter().enumerate() {
+            set(body_plane, p, (j + 1) as f32 / size);
+        }
+        let head_plane = match i {
+            i if i == index => 1,
+            _ if snake.size() >= me.size() => 3,
+            _ => 4,
+        };
+        set(head_plane, &snake.head(), 1.0);
+    }
+    for food in board.food.iter() {
+        set(5, food, 1.0);
+    }
+    for hazard in hazards.iter() {
+        set(6, hazard, 1.0);
+    }
+    let health = me.health as f32 / 100.0;
+    planes[7 * area..].iter_mut().for_each(|v| *v = health);
+    planes
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::GameInfo;
+    use super::*;
+
+    #[test]
+    fn planes() {
+        let board = Board::from_api(&GameInfo::new(
+            r#"{
+            "game": {"id": "game-1", "timeout": 500},
+            "turn": 5,
+            "board": {
+                "height": 5,
+                "width": 7,
+                "food": [{"x": 6, "y": 4}],
+                "hazards": [],
+                "snakes": [
+                    {"id": "me", "name": "me", "health": 50, "body": [{"x": 0, "y": 0}, {"x": 1, "y": 0}], "head": {"x": 0, "y": 0}, "length": 2, "shout": ""},
+                    {"id": "other", "name": "other", "health": 90, "body": [{"x": 3, "y": 3}, {"x": 3, "y": 2}, {"x": 3, "y": 1}], "head": {"x": 3, "y": 3}, "length": 3, "shout": ""}
+                ]
+            },
+            "you": {"id": "me", "name": "me", "health": 50, "body": [{"x": 0, "y": 0}, {"x": 1, "y": 0}], "head": {"x": 0, "y": 0}, "length": 2, "shout": ""}
+        }"#,
+        ));
+        let hazards = vec![Point::new(6, 0)];
+        let planes = encode(&board, 0, &hazards);
+        let area = 35;
+        assert_eq!(planes.len(), PLANES * area);
+        let at = |plane: usize, x: usize, y: usize| planes[plane * area + y * 7 + x];
+        assert_eq!(at(0, 0, 0), 0.5);
+        assert_eq!(at(0, 1, 0), 1.0);
+        assert_eq!(at(1, 0, 0), 1.0);
+        assert!((at(2, 3, 2) - 2.0 / 3.0).abs() < 1e-6);
+        assert_eq!(at(3, 3, 3), 1.0);
+        assert_eq!(at(4, 3, 3), 0.0);
+        assert_eq!(at(5, 6, 4), 1.0);
+        assert_eq!(at(6, 6, 0), 1.0);
+        assert_eq!(at(7, 4, 4), 0.5);
+        // from the other snake's view we are the shorter one
+        let planes = encode(&board, 1, &hazards);
+        assert_eq!(planes[4 * area], 1.0);
+    }
+}
@@ -0,0 +1,243 @@
+//! Game recordings
+//!
+//! Every game is stored as a JSON lines file `<game id>.jsonl` in the record
+//! directory. Each `/move` appends a `move` record with the request as we
+//! received it, the move we sent and scores of the root moves, `/end` appends
+//! an `end` record with the final board.
+//!
+//! A file is never shared by two games: if `<game id>.jsonl` exists already,
+//! from an earlier run or another id sanitised to the same name, the game goes
+//! to `<game id>-<n>.jsonl` instead.
+//!
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::Direction;
+use super::GameInfo;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ReplayRecord {
+    Move {
+        state: GameInfo,
+        chosen: Direction,
+        /// search scores of root moves, indexed by `Direction::as_index`
+        scores: [Option<f32>; 4],
+    },
+    End {
+        state: GameInfo,
+    },
+}
+
+impl ReplayRecord {
+    pub fn get_state(&self) -> &GameInfo {
+        match self {
+            ReplayRecord::Move { state, .. } | ReplayRecord::End { state } => state,
+        }
+    }
+}
+
+/// Appends records of running games to files in a directory
+#[derive(Debug)]
+pub struct Recorder {
+    dir: PathBuf,
+    /// file of each running game, by game id
+    files: Mutex<HashMap<String, PathBuf>>,
+}
+
+impl Recorder {
+    pub fn new(dir: &str) -> Result<Self, String> {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir, e))?;
+        Ok(Self {
+            dir: PathBuf::from(dir),
+            files: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Creates a new file for the game, suffixed if the name is taken
+    fn create(&self, id: &str) -> Result<PathBuf, String> {
+        // game ids come from the request, keep them from escaping the directory
+        let name: String = id
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect();
+        for n in 0.. {
+            let path = match n {
+                0 => self.dir.join(format!("{}.jsonl", name)),
+                n => self.dir.join(format!("{}-{}.jsonl", name, n)),
+            };
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(path),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(format!("Cannot create {}: {}", path.display(), e)),
+            }
+        }
+        unreachable!()
+    }
+
+    pub fn record(&self, record: &ReplayRecord) -> Result<(), String> {
+        let id = record.get_state().get_game_id();
+        let mut files = self.files.lock().unwrap();
+        let path = match files.get(&id) {
+            Some(path) => path.clone(),
+            None => {
+                let path = self.create(&id)?;
+                files.insert(id.clone(), path.clone());
+                path
+            }
+        };
+        if let ReplayRecord::End { .. } = record {
+            files.remove(&id);
+        }
+        std::mem::drop(files);
+        let line = serde_json::to_string(record).map_err(|e| e.to_string())?;
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| writeln!(file, "{}", line))
+            .map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+    }
+}
+
+/// Single recorded game
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub records: Vec<ReplayRecord>,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        let records = data
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<ReplayRecord>, _>>()
+            .map_err(|e| format!("Invalid replay {}: {}", path.display(), e))?;
+        Ok(Self { records })
+    }
+
+    /// Loads all `.jsonl` replays in a directory, sorted by file name
+    pub fn load_dir(dir: &str) -> Result<Vec<Self>, String> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+            .map_err(|e| format!("Cannot read {}: {}", dir, e))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| matches!(path.extension(), Some(ext) if ext == "jsonl"))
+            .collect();
+        paths.sort();
+        paths.iter().map(|path| Self::load(path)).collect()
+    }
+
+    /// Positions we moved from, in the order of turns
+    pub fn get_moves(&self) -> Vec<(&GameInfo, Direction)> {
+        self.records
+            .iter()
+            .filter_map(|record| match record {
+                ReplayRecord::Move { state, chosen, .. } => Some((state, *chosen)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// 1.0 for a win, 0.0 for a loss and 0.5 for a draw, nothing if the game didn't end
+    pub fn get_outcome(&self) -> Option<f32> {
+        let state = self.records.iter().rev().find_map(|record| match record {
+            ReplayRecord::End { state } => Some(state),
+            _ => None,
+        })?;
+        let alive = state.board.snakes.iter().any(|s| s.id == state.you.id);
+        match (alive, state.board.snakes.len()) {
+            (false, _) => Some(0.0),
+            (true, 1) => Some(1.0),
+            _ => Some(0.5),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn state(turn: i32, enemy_alive: bool) -> GameInfo {
+        let enemy = r#",{"id": "enemy", "name": "Other", "health": 90, "body": [{"x": 5, "y": 5}, {"x": 5, "y": 4}, {"x": 5, "y": 3}], "head": {"x": 5, "y": 5}, "length": 3, "shout": ""}"#;
+        GameInfo::new(&format!(
+            r#"{{
+            "game": {{"id": "../game-1", "timeout": 500}},
+            "turn": {},
+            "board": {{
+                "height": 11,
+                "width": 11,
+                "food": [],
+                "hazards": [],
+                "snakes": [
+                    {{"id": "me", "name": "Go  Giddy", "health": 90, "body": [{{"x": 1, "y": 1}}, {{"x": 1, "y": 2}}, {{"x": 1, "y": 3}}], "head": {{"x": 1, "y": 1}}, "length": 3, "shout": ""}}{}
+                ]
+            }},
+            "you": {{"id": "me", "name": "Go  Giddy", "health": 90, "body": [{{"x": 1, "y": 1}}, {{"x": 1, "y": 2}}, {{"x": 1, "y": 3}}], "head": {{"x": 1, "y": 1}}, "length": 3, "shout": ""}}
+        }}"#,
+            turn,
+            if enemy_alive { enemy } else { "" }
+        ))
+    }
+
+    #[test]
+    fn record_and_load() {
+        let dir = std::env::temp_dir().join(format!("battlesnake-replays-{}", std::process::id()));
+        let recorder = Recorder::new(dir.to_str().unwrap()).unwrap();
+        for turn in 0..3 {
+            recorder
+                .record(&ReplayRecord::Move {
+                    state: state(turn, true),
+                    chosen: Direction::Down,
+                    scores: [Some(0.5), None, None, Some(0.7)],
+                })
+                .unwrap();
+        }
+        let replays = Replay::load_dir(dir.to_str().unwrap()).unwrap();
+        assert_eq!(replays.len(), 1);
+        assert_eq!(replays[0].get_moves().len(), 3);
+        assert_eq!(replays[0].get_moves()[2].0.get_turn(), 2);
+        assert_eq!(replays[0].get_outcome(), None);
+
+        recorder
+            .record(&ReplayRecord::End {
+                state: state(3, false),
+            })
+            .unwrap();
+        // id with path separators stays inside the directory
+        let replay = Replay::load(&dir.join("game-1.jsonl")).unwrap();
+        assert_eq!(replay.records.len(), 4);
+        assert_eq!(replay.get_outcome(), Some(1.0));
+
+        // the same id again, e.g. after a restart, doesn't append to the finished game
+        let recorder = Recorder::new(dir.to_str().unwrap()).unwrap();
+        for turn in 0..2 {
+            recorder
+                .record(&ReplayRecord::Move {
+                    state: state(turn, true),
+                    chosen: Direction::Down,
+                    scores: [None; 4],
+                })
+                .unwrap();
+        }
+        assert_eq!(Replay::load(&dir.join("game-1.jsonl")).unwrap().records.len(), 4);
+        assert_eq!(Replay::load(&dir.join("game-1-1.jsonl")).unwrap().records.len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn outcome() {
+        let end = |state| Replay {
+            records: vec![ReplayRecord::End { state }],
+        };
+        assert_eq!(end(state(10, true)).get_outcome(), Some(0.5));
+        let mut lost = state(10, true);
+        lost.board.snakes.remove(0);
+        assert_eq!(end(lost).get_outcome(), Some(0.0));
+    }
+}
@@ -0,0 +1,147 @@
+//! Tail-chasing safety check
+//!
+//! A move is safe when it leaves a way back to our own body that gets there no
+//! sooner than the body cell is vacated. From there the snake can follow its
+//! own tail for as long as it likes, whatever the other snakes do around it.
+//! Other snakes are assumed to stay on their paths, same as in the flood fill,
+//! and eating on the move keeps our tail in place one turn longer.
+//!
+//! The check doesn't pick moves by itself, it vetoes the choice of a strategy
+//! when there is a safe alternative the search didn't see us die on. A move
+//! the search scored as a win is never vetoed.
+//!
+use std::collections::VecDeque;
+
+use super::evaluator::WIN_SCORE;
+use super::minimax::DeathScores;
+use super::Board;
+use super::Direction;
+use super::ALL_DIRECTIONS;
+
+impl Board {
+    /// Whether moving snake `index` in `dir` leaves it a way back to its tail
+    pub fn is_safe_move(&self, index: usize, dir: Direction) -> bool {
+        let snake = &self.snakes[index];
+        let head = snake.head() + dir;
+        if !self.is_free(&head, 1) {
+            return false;
+        }
+        let eats = self.food.contains(&head) as usize;
+        let mut times = self.get_vacate_times();
+        let mut own = vec![false; times.len()];
+        for p in snake.body.nodes.iter().filter(|p| self.is_inbounds(p)) {
+            own[self.get_index(p)] = true;
+        }
+        for (cell, time) in times.iter_mut().enumerate() {
+            if own[cell] {
+                *time += eats;
+            }
+        }
+
+        let start = self.get_index(&head);
+        let mut visited = vec![false; times.len()];
+        visited[start] = true;
+        let mut queue: VecDeque<(usize, usize)> = VecDeque::new();
+        queue.push_back((start, 1));
+        while let Some((cell, turn)) = queue.pop_front() {
+            for n in self.get_point(cell).get_neighbours() {
+                if !self.is_inbounds(&n) {
+                    continue;
+                }
+                let j = self.get_index(&n);
+                if visited[j] || times[j] > turn + 1 {
+                    continue;
+                }
+                if own[j] {
+                    return true;
+                }
+                visited[j] = true;
+                queue.push_back((j, turn + 1));
+            }
+        }
+        false
+    }
+
+    /// Keeps `dir` of snake `index` if it is safe or wins, otherwise the safe
+    /// move with the best score above `death_scores`, indexed by
+    /// `Direction::as_index`, or `dir` again if there is none
+    pub fn veto_move(
+        &self,
+        index: usize,
+        dir: Direction,
+        scores: &[Option<f32>; 4],
+        death_scores: &DeathScores,
+    ) -> Direction {
+        let wins = matches!(scores[dir.as_index()], Some(s) if s >= WIN_SCORE);
+        if wins || self.is_safe_move(index, dir) {
+            return dir;
+        }
+        ALL_DIRECTIONS
+            .iter()
+            .filter_map(|d| scores[d.as_index()].map(|s| (*d, s)))
+            .filter(|(d, s)| *s > death_scores.max() && self.is_safe_move(index, *d))
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+            .map_or(dir, |(d, _)| d)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::GameInfo;
+    use super::super::Point;
+    use super::*;
+
+    #[test]
+    fn tail_chasing() {
+        // going up leads into a pocket closed by the other snake and our own body
+        let state = r#"{
+            "game": {"id": "game-1", "timeout": 500},
+            "turn": 5,
+            "board": {
+                "height": 5,
+                "width": 5,
+                "food": [],
+                "hazards": [],
+                "snakes": [
+                    {"id": "me", "name": "me", "health": 90, "body": [
+                        {"x": 0, "y": 1}, {"x": 1, "y": 1}, {"x": 1, "y": 2}, {"x": 1, "y": 3}, {"x": 2, "y": 3}
+                    ], "head": {"x": 0, "y": 1}, "length": 5, "shout": ""},
+                    {"id": "other", "name": "other", "health": 90, "body": [
+                        {"x": 0, "y": 3}, {"x": 0, "y": 4}, {"x": 1, "y": 4}, {"x": 2, "y": 4}, {"x": 3, "y": 4}
+                    ], "head": {"x": 0, "y": 3}, "length": 5, "shout": ""}
+                ]
+            },
+            "you": {"id": "me", "name": "me", "health": 90, "body": [
+                {"x": 0, "y": 1}, {"x": 1, "y": 1}, {"x": 1, "y": 2}, {"x": 1, "y": 3}, {"x": 2, "y": 3}
+            ], "head": {"x": 0, "y": 1}, "length": 5, "shout": ""}
+        }"#;
+        let board = Board::from_api(&GameInfo::new(state));
+        assert!(!board.is_safe_move(0, Direction::Up));
+        assert!(board.is_safe_move(0, Direction::Down));
+        assert!(!board.is_safe_move(0, Direction::Right));
+        let deaths = DeathScores::default();
+        let scores = [Some(0.1), None, Some(0.9), Some(0.2)];
+        assert_eq!(board.veto_move(0, Direction::Up, &scores, &deaths), Direction::Down);
+        assert_eq!(
+            board.veto_move(0, Direction::Down, &scores, &deaths),
+            Direction::Down
+        );
+        // the only safe move loses the head to head
+        let losing = [Some(0.1), None, Some(0.9), Some(deaths.head_to_head)];
+        assert_eq!(board.veto_move(0, Direction::Up, &losing, &deaths), Direction::Up);
+        // or wasn't searched
+        let unknown = [Some(0.1), None, Some(0.9), None];
+        assert_eq!(board.veto_move(0, Direction::Up, &unknown, &deaths), Direction::Up);
+        // a win is never vetoed
+        let winning = [Some(0.1), None, Some(WIN_SCORE), Some(0.2)];
+        assert_eq!(board.veto_move(0, Direction::Up, &winning, &deaths), Direction::Up);
+
+        // one segment shorter, our body leaves the pocket in time
+        let mut board = board;
+        board.snakes[0].body.nodes.truncate(4);
+        assert!(board.is_safe_move(0, Direction::Up));
+        // unless we eat on the way in
+        board.food.push(Point::new(0, 2));
+        assert!(!board.is_safe_move(0, Direction::Up));
+    }
+}
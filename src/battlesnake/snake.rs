@@ -7,7 +7,9 @@ use super::path::Path;
 use super::point::Point;
 use super::Direction;
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Customization and metadata answered on `/`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
 pub struct SnakeProps {
     apiversion: String,
     author: String,
@@ -25,17 +27,64 @@ impl SnakeProps {
             color: String::from("#622BAA"),
             head: String::from("evil"),
             tail: String::from("rattle"),
-            version: String::from("0.1.0"),
+            version: String::from(env!("CARGO_PKG_VERSION")),
         }
     }
 
+    /// Sets `field` to `value`, e.g. `color` to `#622BAA`, unchanged if invalid
+    pub fn set(&mut self, field: &str, value: &str) -> Result<(), String> {
+        let mut props = self.clone();
+        let target = match field {
+            "apiversion" => &mut props.apiversion,
+            "author" => &mut props.author,
+            "color" => &mut props.color,
+            "head" => &mut props.head,
+            "tail" => &mut props.tail,
+            "version" => &mut props.version,
+            _ => return Err(format!("Unknown snake field \"{}\"", field)),
+        };
+        *target = value.to_string();
+        props.validate()?;
+        *self = props;
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.apiversion != "1" {
+            return Err(format!("Unsupported API version \"{}\"", self.apiversion));
+        }
+        let hex = self.color.strip_prefix('#').unwrap_or_default();
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Invalid color \"{}\", expected #RRGGBB", self.color));
+        }
+        // names of the customizations are lowercase words joined by hyphens
+        for name in [&self.head, &self.tail].iter() {
+            let valid = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+            if !valid {
+                return Err(format!("Invalid customization \"{}\"", name));
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_string(&self) -> String {
         serde_json::to_string(&self).unwrap()
     }
 }
 
+impl Default for SnakeProps {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Snake {
+    /// Index of the snake on the root board, stays the same when other snakes die during search
+    pub id: usize,
     pub health: u8,
     pub body: Path,
 }
@@ -98,6 +147,7 @@ impl TryFrom<&ApiSnake> for Snake {
         }
         let body = Path::from(&input.body);
         Ok(Snake {
+            id: 0,
             health: input.health as u8,
             body,
         })
@@ -111,6 +161,7 @@ mod test {
     #[test]
     fn basics() {
         let mut snake = Snake {
+            id: 0,
             health: 50,
             body: Path::from_vec(vec![
                 Point { x: 9, y: 3 },
@@ -165,4 +216,25 @@ mod test {
         assert_eq!(snake.is_collision(&Point { x: 7, y: 5 }), Some(5));
         assert_eq!(snake.is_collision(&Point { x: 7, y: 6 }), None);
     }
+
+    #[test]
+    fn props() {
+        let mut props = SnakeProps::default();
+        assert_eq!(props.validate(), Ok(()));
+        assert_eq!(props.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(props.set("color", "#00ff7F"), Ok(()));
+        assert_eq!(props.set("tail", "round-bum"), Ok(()));
+        assert!(props.get_string().contains("\"tail\":\"round-bum\""));
+        assert!(props.set("colour", "#000000").is_err());
+        for color in ["red", "#12345", "#1234567", "#GG0000", "123456"].iter() {
+            assert!(props.set("color", color).is_err(), "{}", color);
+        }
+        for name in ["", "Evil", "evil head", "evil_head"].iter() {
+            assert!(props.set("head", name).is_err(), "{}", name);
+        }
+        assert!(props.set("apiversion", "0").is_err());
+        // invalid values are not taken
+        assert_eq!(props.validate(), Ok(()));
+        assert_eq!(props.color, "#00ff7F");
+    }
 }
@@ -0,0 +1,343 @@
+//! Space filling for a snake with nowhere left to go
+//!
+//! Once our region is sealed off, or too small for our body because other
+//! snakes get to most of it first, fighting is over and the best we can do is
+//! to survive as many turns as possible. The survival solver runs a depth
+//! first search with a node budget for the longest path through the region:
+//!
+//! - cells of the path are blocked until our tail has passed them, cells of
+//!   other snakes until they are vacated, same as in the flood fill
+//! - food on the way restores health and grows the tail by one
+//! - the move into a hazard costs the hazard damage on top, unless it eats
+//! - the first move doesn't go next to the head of a snake at least as long,
+//!   which would win the head to head
+//!
+//! Cells with fewer free neighbours are tried first to hug walls and bodies,
+//! food first when health runs short of the horizon. A branch is dropped once
+//! it can't beat the best path found, as every turn costs health and only
+//! the food left can restore it, and the search is over as soon as a path
+//! reaches the horizon.
+//!
+use super::distance::DistanceMaps;
+use super::Board;
+use super::Direction;
+use super::Point;
+use super::ALL_DIRECTIONS;
+
+const MAX_HEALTH: usize = 100;
+
+pub struct SurvivalSolver {
+    /// cells searched at most
+    pub budget: usize,
+    /// turns after which a path counts as survived
+    pub horizon: usize,
+    /// health lost per turn in hazard on top of the regular turn
+    pub hazard_damage: usize,
+}
+
+impl SurvivalSolver {
+    /// Solver with the default budget and horizon, `hazard_damage` as in
+    /// the evaluator settings
+    pub fn new(hazard_damage: f32) -> Self {
+        Self {
+            budget: 20_000,
+            horizon: MAX_HEALTH,
+            hazard_damage: hazard_damage.ceil() as usize,
+        }
+    }
+}
+
+struct Search<'a> {
+    board: &'a Board,
+    hazard: Vec<bool>,
+    food: Vec<bool>,
+    food_left: usize,
+    /// turn at which cells of other snakes are vacated
+    others: Vec<usize>,
+    /// turn at which our head last entered each cell
+    entered: Vec<Option<isize>>,
+    size: usize,
+    horizon: usize,
+    hazard_damage: usize,
+    budget: usize,
+}
+
+impl<'a> Search<'a> {
+    fn is_open(&self, cell: usize, turn: usize) -> bool {
+        self.others[cell] <= turn
+            && self.entered[cell].map_or(true, |e| turn as isize - e >= self.size as isize)
+    }
+
+    /// Health after moving into `cell`, none if the snake doesn't survive it
+    fn get_health(&self, cell: usize, health: usize) -> Option<usize> {
+        // eating heals fully, even on a hazard
+        if self.food[cell] {
+            return Some(MAX_HEALTH);
+        }
+        let damage = if self.hazard[cell] {
+            self.hazard_damage
+        } else {
+            0
+        };
+        health.checked_sub(1 + damage).filter(|h| *h > 0)
+    }
+
+    /// Open cells around `cell` on `turn` and the health after moving there
+    fn get_moves(&self, cell: usize, turn: usize, health: usize) -> Vec<(usize, usize)> {
+        self.board
+            .get_point(cell)
+            .get_neighbours()
+            .iter()
+            .filter(|n| self.board.is_inbounds(n))
+            .map(|n| self.board.get_index(n))
+            .filter(|n| self.is_open(*n, turn))
+            .filter_map(|n| self.get_health(n, health).map(|h| (n, h)))
+            .collect()
+    }
+
+    /// Length of the longest path found continuing from `cell` entered on `turn`
+    fn search(&mut self, cell: usize, turn: usize, health: usize) -> usize {
+        let entered = self.entered[cell];
+        let eats = self.food[cell];
+        self.entered[cell] = Some(turn as isize);
+        if eats {
+            self.food[cell] = false;
+            self.food_left -= 1;
+            self.size += 1;
+        }
+
+        let mut best = turn;
+        let bound = self
+            .horizon
+            .min(turn + health + self.food_left * MAX_HEALTH);
+        if turn < self.horizon && self.budget > 0 {
+            self.budget -= 1;
+            let hungry = health < self.horizon - turn;
+            let mut moves = self.get_moves(cell, turn + 1, health);
+            moves.sort_by_key(|(n, h)| {
+                (
+                    !(hungry && self.food[*n]),
+                    self.get_moves(*n, turn + 2, *h).len(),
+                )
+            });
+            for (n, h) in moves {
+                best = best.max(self.search(n, turn + 1, h));
+                if best >= bound {
+                    break;
+                }
+            }
+        }
+
+        if eats {
+            self.food[cell] = true;
+            self.food_left += 1;
+            self.size -= 1;
+        }
+        self.entered[cell] = entered;
+        best
+    }
+}
+
+impl SurvivalSolver {
+    /// First move and length of the longest path of snake `index` found
+    pub fn solve(
+        &self,
+        board: &Board,
+        index: usize,
+        hazards: &[Point],
+    ) -> Option<(Direction, usize)> {
+        let snake = &board.snakes[index];
+        let head = snake.head();
+        if !board.is_inbounds(&head) {
+            return None;
+        }
+        let cells = board.get_width() * board.get_height();
+        let mut search = Search {
+            board,
+            hazard: vec![false; cells],
+            food: vec![false; cells],
+            food_left: 0,
+            others: vec![0; cells],
+            entered: vec![None; cells],
+            size: snake.size(),
+            horizon: self.horizon,
+            hazard_damage: self.hazard_damage,
+            budget: 0,
+        };
+        for p in hazards.iter().filter(|p| board.is_inbounds(p)) {
+            search.hazard[board.get_index(p)] = true;
+        }
+        for p in board.food.iter().filter(|p| board.is_inbounds(p)) {
+            search.food[board.get_index(p)] = true;
+            search.food_left += 1;
+        }
+        // walking from the tail keeps the lowest index for stacked segments
+        for (i, other) in board.snakes.iter().enumerate() {
+            for (j, p) in other.body.nodes.iter().enumerate().rev() {
+                if !board.is_inbounds(p) {
+                    continue;
+                }
+                if i == index {
+                    search.entered[board.get_index(p)] = Some(-(j as isize));
+                } else {
+                    search.others[board.get_index(p)] = other.size() - j;
+                }
+            }
+        }
+
+        // heads that win or tie a head to head on our first move
+        let threats: Vec<Point> = board
+            .snakes
+            .iter()
+            .enumerate()
+            .filter(|(i, other)| *i != index && other.size() >= snake.size())
+            .flat_map(|(_, other)| other.head().get_neighbours())
+            .collect();
+
+        let start = board.get_index(&head);
+        let mut best: Option<(Direction, usize)> = None;
+        for (n, health) in search.get_moves(start, 1, snake.health as usize) {
+            if threats.contains(&board.get_point(n)) {
+                continue;
+            }
+            search.budget = self.budget / ALL_DIRECTIONS.len();
+            let length = search.search(n, 1, health);
+            if best.map_or(true, |(_, l)| length > l) {
+                let dir = head.get_neighbour_direction(board.get_point(n)).unwrap();
+                best = Some((dir, length));
+            }
+            if length >= self.horizon {
+                break;
+            }
+        }
+        best
+    }
+}
+
+impl Board {
+    /// Whether no other snake can get to the cells snake `index` can reach
+    /// before it had the time to fill all of them
+    pub fn is_sealed(&self, distances: &DistanceMaps, index: usize) -> bool {
+        let area = distances.get_reachable(index);
+        let mine = distances.get_steps_map(index);
+        (0..self.snakes.len()).filter(|i| *i != index).all(|i| {
+            distances
+                .get_steps_map(i)
+                .iter()
+                .zip(mine.iter())
+                .all(|(theirs, mine)| mine.is_none() || theirs.map_or(true, |t| t > area))
+        })
+    }
+
+    /// Whether the cells snake `index` gets to before any other snake
+    /// are too few to fit its body
+    pub fn is_trapped(&self, distances: &DistanceMaps, index: usize) -> bool {
+        let first = distances
+            .get_steps_map(index)
+            .iter()
+            .enumerate()
+            .filter(|(cell, steps)| match steps {
+                Some(steps) if *steps > 0 => (0..self.snakes.len())
+                    .filter(|i| *i != index)
+                    .all(|i| distances.get_steps_map(i)[*cell].map_or(true, |t| t > *steps)),
+                _ => false,
+            })
+            .count();
+        first < self.snakes[index].size()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::GameInfo;
+    use super::*;
+
+    #[test]
+    fn sealed() {
+        // walled in at the bottom left by a long snake
+        let board = Board::from_api(&GameInfo::new(
+            r#"{
+            "game": {"id": "game-1", "timeout": 500},
+            "turn": 5,
+            "board": {
+                "height": 7,
+                "width": 7,
+                "food": [],
+                "hazards": [],
+                "snakes": [
+                    {"id": "me", "name": "me", "health": 90, "body": [{"x": 1, "y": 1}, {"x": 1, "y": 0}, {"x": 0, "y": 0}], "head": {"x": 1, "y": 1}, "length": 3, "shout": ""},
+                    {"id": "other", "name": "other", "health": 90, "body": [
+                        {"x": 4, "y": 0}, {"x": 3, "y": 0}, {"x": 3, "y": 1}, {"x": 3, "y": 2}, {"x": 3, "y": 3}, {"x": 2, "y": 3}, {"x": 1, "y": 3},
+                        {"x": 0, "y": 3}, {"x": 0, "y": 4}, {"x": 0, "y": 5}, {"x": 0, "y": 6}, {"x": 1, "y": 6}, {"x": 2, "y": 6}, {"x": 3, "y": 6},
+                        {"x": 4, "y": 6}, {"x": 5, "y": 6}, {"x": 6, "y": 6}, {"x": 6, "y": 5}, {"x": 6, "y": 4}, {"x": 6, "y": 3}
+                    ], "head": {"x": 4, "y": 0}, "length": 20, "shout": ""}
+                ]
+            },
+            "you": {"id": "me", "name": "me", "health": 90, "body": [{"x": 1, "y": 1}, {"x": 1, "y": 0}, {"x": 0, "y": 0}], "head": {"x": 1, "y": 1}, "length": 3, "shout": ""}
+        }"#,
+        ));
+        let distances = board.get_distance_maps(&[], 1);
+        assert_eq!(distances.get_reachable(0), 8);
+        assert!(board.is_sealed(&distances, 0));
+        assert!(!board.is_trapped(&distances, 0));
+        // a short snake doesn't wall us in for long
+        let mut open = board.clone();
+        open.snakes[1].body.nodes.truncate(8);
+        assert!(!open.is_sealed(&open.get_distance_maps(&[], 1), 0));
+
+        // chasing our tail around the pocket lasts until we starve
+        let solver = SurvivalSolver::new(15.0);
+        let (_, length) = solver.solve(&board, 0, &[]).unwrap();
+        assert_eq!(length, 89);
+        let mut starving = board;
+        starving.snakes[0].health = 3;
+        assert_eq!(solver.solve(&starving, 0, &[]).unwrap().1, 2);
+        // unless there is food on the way
+        starving.food.push(Point::new(2, 2));
+        assert_eq!(solver.solve(&starving, 0, &[]).unwrap().1, solver.horizon);
+        // that can't be reached through hazard
+        let hazards = vec![Point::new(1, 2), Point::new(2, 1)];
+        assert_eq!(solver.solve(&starving, 0, &hazards).unwrap().1, 2);
+        // unless the hazard is harmless
+        let harmless = SurvivalSolver::new(0.0);
+        assert_eq!(harmless.solve(&starving, 0, &hazards).unwrap().1, solver.horizon);
+        // or the food itself is on it, eating heals all the same
+        let deadly = SurvivalSolver::new(100.0);
+        let hazards = vec![Point::new(2, 2)];
+        assert_eq!(deadly.solve(&starving, 0, &hazards).unwrap().1, deadly.horizon);
+    }
+
+    #[test]
+    fn trapped() {
+        // the other snake gets to the only free cell as soon as we do
+        let board = Board::from_api(&GameInfo::new(
+            r#"{
+            "game": {"id": "game-1", "timeout": 500},
+            "turn": 5,
+            "board": {
+                "height": 1,
+                "width": 7,
+                "food": [],
+                "hazards": [],
+                "snakes": [
+                    {"id": "me", "name": "me", "health": 90, "body": [{"x": 2, "y": 0}, {"x": 1, "y": 0}, {"x": 0, "y": 0}], "head": {"x": 2, "y": 0}, "length": 3, "shout": ""},
+                    {"id": "other", "name": "other", "health": 90, "body": [{"x": 4, "y": 0}, {"x": 5, "y": 0}, {"x": 6, "y": 0}], "head": {"x": 4, "y": 0}, "length": 3, "shout": ""}
+                ]
+            },
+            "you": {"id": "me", "name": "me", "health": 90, "body": [{"x": 2, "y": 0}, {"x": 1, "y": 0}, {"x": 0, "y": 0}], "head": {"x": 2, "y": 0}, "length": 3, "shout": ""}
+        }"#,
+        ));
+        let distances = board.get_distance_maps(&[], 1);
+        assert!(board.is_trapped(&distances, 0));
+        assert!(!board.is_sealed(&distances, 0));
+        // and would win the head to head
+        let solver = SurvivalSolver::new(15.0);
+        assert_eq!(solver.solve(&board, 0, &[]), None);
+        let mut shorter = board;
+        shorter.snakes[1].body.nodes.pop();
+        assert_eq!(
+            solver.solve(&shorter, 0, &[]).map(|(dir, _)| dir),
+            Some(Direction::Right)
+        );
+    }
+}
@@ -0,0 +1,232 @@
+//! Decision engines behind the API
+//!
+//! A `Strategy` picks our moves and gets to see the start and the end of every
+//! game. Which one plays is a setting like the search parameters, so it can
+//! differ per ruleset:
+//!
+//! - `minimax`: the best-first search over all snakes' moves
+//! - `astar`: the original A* to the nearest safe food with fallbacks
+//!
+//! Whichever strategy plays, `validate_move` gets the last word on the move
+//! before it goes out.
+//!
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use super::config::Settings;
+use super::evaluator::Evaluator;
+use super::game::GameContext;
+use super::minimax::get_move;
+use super::Board;
+use super::GameInfo;
+use super::Move;
+
+pub trait Strategy: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Called on `/start`, before the first move
+    fn start(&self, _gameinfo: &GameInfo, _ctx: &mut GameContext) {}
+
+    fn get_move(
+        &self,
+        gameinfo: &GameInfo,
+        ctx: &mut GameContext,
+        time_budget: Duration,
+        settings: &Settings,
+        evaluator: &dyn Evaluator,
+    ) -> Move;
+
+    /// Called on `/end`, the context is dropped afterwards
+    fn end(&self, _gameinfo: &GameInfo, _ctx: &mut GameContext) {}
+}
+
+/// Replaces the move of a strategy by a less lethal one, if there is any,
+/// and drops the rest of the plan along with it
+pub fn validate_move(
+    gameinfo: &GameInfo,
+    ctx: &mut GameContext,
+    settings: &Settings,
+    movement: Move,
+) -> Move {
+    let board = Board::from_api(gameinfo);
+    let scores = ctx.search.as_ref().map(|s| s.root_scores).unwrap_or_default();
+    let valid = board.validate_move(
+        0,
+        movement.movement,
+        gameinfo.get_hazards(),
+        settings.evaluator.hazard_damage,
+        &scores,
+    );
+    if valid == movement.movement {
+        return movement;
+    }
+    debug!("Replaced {:?} by the less lethal {:?}", movement.movement, valid);
+    ctx.plan = vec![valid];
+    Move {
+        movement: valid,
+        shout: movement.shout,
+    }
+}
+
+pub struct Minimax;
+
+impl Strategy for Minimax {
+    fn name(&self) -> &'static str {
+        "minimax"
+    }
+
+    fn get_move(
+        &self,
+        gameinfo: &GameInfo,
+        ctx: &mut GameContext,
+        time_budget: Duration,
+        settings: &Settings,
+        evaluator: &dyn Evaluator,
+    ) -> Move {
+        get_move(gameinfo, ctx, time_budget, settings, evaluator)
+    }
+}
+
+pub struct Astar;
+
+impl Strategy for Astar {
+    fn name(&self) -> &'static str {
+        "astar"
+    }
+
+    fn get_move(
+        &self,
+        gameinfo: &GameInfo,
+        ctx: &mut GameContext,
+        _time_budget: Duration,
+        settings: &Settings,
+        _evaluator: &dyn Evaluator,
+    ) -> Move {
+        let movement = Move::new(gameinfo, settings);
+        // nothing searched, don't leave last turn's line around as a plan
+        ctx.plan = vec![movement.movement];
+        ctx.search = None;
+        movement
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum StrategyKind {
+    #[default]
+    Minimax,
+    Astar,
+}
+
+impl StrategyKind {
+    pub fn build(&self) -> Box<dyn Strategy> {
+        match self {
+            StrategyKind::Minimax => Box::new(Minimax),
+            StrategyKind::Astar => Box::new(Astar),
+        }
+    }
+}
+
+impl FromStr for StrategyKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "minimax" => Ok(StrategyKind::Minimax),
+            "astar" => Ok(StrategyKind::Astar),
+            _ => Err(format!("Unknown strategy \"{}\"", s)),
+        }
+    }
+}
+
+impl fmt::Display for StrategyKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.build().name())
+    }
+}
+
+impl TryFrom<String> for StrategyKind {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<StrategyKind> for String {
+    fn from(kind: StrategyKind) -> Self {
+        kind.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn strategies() {
+        let gameinfo = GameInfo::new(
+            r#"{
+            "game": {"id": "game-1", "timeout": 500},
+            "turn": 5,
+            "board": {
+                "height": 7,
+                "width": 7,
+                "food": [{"x": 5, "y": 1}],
+                "hazards": [],
+                "snakes": [
+                    {"id": "me", "name": "me", "health": 90, "body": [{"x": 1, "y": 1}, {"x": 1, "y": 2}, {"x": 1, "y": 3}], "head": {"x": 1, "y": 1}, "length": 3, "shout": ""},
+                    {"id": "other", "name": "other", "health": 90, "body": [{"x": 5, "y": 5}, {"x": 5, "y": 4}, {"x": 5, "y": 3}], "head": {"x": 5, "y": 5}, "length": 3, "shout": ""}
+                ]
+            },
+            "you": {"id": "me", "name": "me", "health": 90, "body": [{"x": 1, "y": 1}, {"x": 1, "y": 2}, {"x": 1, "y": 3}], "head": {"x": 1, "y": 1}, "length": 3, "shout": ""}
+        }"#,
+        );
+        let board = Board::from_api(&gameinfo);
+        let legal = board.get_pruned_moves(&board.snakes[0].head(), 1);
+        let settings = Settings::default();
+        let evaluator = settings.evaluator.build();
+        for kind in [StrategyKind::Minimax, StrategyKind::Astar].iter() {
+            let strategy = kind.build();
+            let mut ctx = GameContext::new(&gameinfo, Arc::default());
+            strategy.start(&gameinfo, &mut ctx);
+            let movement = strategy.get_move(
+                &gameinfo,
+                &mut ctx,
+                Duration::from_millis(20),
+                &settings,
+                &evaluator,
+            );
+            assert!(legal.contains(&movement.movement), "{}", kind);
+            assert_eq!(ctx.plan.first(), Some(&movement.movement));
+            strategy.end(&gameinfo, &mut ctx);
+        }
+        // heading for the food
+        let mut ctx = GameContext::new(&gameinfo, Arc::default());
+        let movement = Astar.get_move(
+            &gameinfo,
+            &mut ctx,
+            Duration::from_millis(20),
+            &settings,
+            &evaluator,
+        );
+        assert_eq!(movement.movement, super::super::Direction::Right);
+        // running into our own body is replaced along with the plan
+        let movement = Move {
+            movement: super::super::Direction::Up,
+            shout: None,
+        };
+        let movement = validate_move(&gameinfo, &mut ctx, &settings, movement);
+        assert!(legal.contains(&movement.movement));
+        assert_eq!(ctx.plan, vec![movement.movement]);
+
+        assert_eq!("astar".parse::<StrategyKind>(), Ok(StrategyKind::Astar));
+        assert!("greedy".parse::<StrategyKind>().is_err());
+        assert_eq!(StrategyKind::default().to_string(), "minimax");
+    }
+}
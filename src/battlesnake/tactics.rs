@@ -0,0 +1,203 @@
+//! Kill opportunities against smaller snakes
+//!
+//! Moves that leave a smaller snake without a way out, rather than just
+//! getting closer to its head:
+//!
+//! - head to head: every move of the target leads into our new head
+//! - wall pin: every move of the target leads into a cell with at most one way
+//!   on, right next to our new head, so we can take that cell head on next turn
+//! - cut off: the move takes the exit of a pocket of the target, too small to
+//!   fit its body, before the target gets out
+//!
+//! Kill moves are scored by the evaluator and tried first in the search, which
+//! takes them from the evaluation of the position instead of finding them again.
+//!
+use super::chokepoint::Chokepoints;
+use super::distance::DistanceMaps;
+use super::Board;
+use super::Direction;
+use super::Point;
+use super::ALL_DIRECTIONS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tactic {
+    HeadToHead,
+    WallPin,
+    CutOff,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KillMove {
+    pub dir: Direction,
+    /// index of the snake being killed
+    pub target: usize,
+    pub tactic: Tactic,
+}
+
+impl Board {
+    /// Cells the head of snake `index` can move to on the next turn
+    fn get_next_cells(&self, index: usize) -> Vec<Point> {
+        let head = self.snakes[index].head();
+        self.get_pruned_moves(&head, 1)
+            .into_iter()
+            .map(|dir| head + dir)
+            .collect()
+    }
+
+    /// Whether snake `target` moving to `cell` can only go on next to `head`
+    fn is_pinned(&self, target: usize, cell: &Point, head: &Point) -> bool {
+        let ways: Vec<Point> = cell
+            .get_neighbours()
+            .iter()
+            .cloned()
+            .filter(|n| *n != self.snakes[target].head() && n != head && self.is_free(n, 2))
+            .collect();
+        ways.len() <= 1 && ways.iter().all(|n| n.is_neighbour(*head))
+    }
+
+    /// Kill moves of snake `index` against each smaller snake
+    pub fn get_kill_moves(
+        &self,
+        distances: &DistanceMaps,
+        chokepoints: &Chokepoints,
+        index: usize,
+    ) -> Vec<KillMove> {
+        let me = &self.snakes[index];
+        let targets: Vec<usize> = (0..self.snakes.len())
+            .filter(|i| *i != index && self.snakes[*i].size() < me.size())
+            .collect();
+        if targets.is_empty() {
+            return Vec::new();
+        }
+        let mut kills = Vec::new();
+        for dir in ALL_DIRECTIONS.iter().cloned() {
+            let head = me.head() + dir;
+            if !self.is_free(&head, 1) {
+                continue;
+            }
+            for target in targets.iter().cloned() {
+                let cells = self.get_next_cells(target);
+                let escapes: Vec<&Point> = cells.iter().filter(|c| **c != head).collect();
+                let tactic = if cells.is_empty() {
+                    // boxed in already, not our doing
+                    continue;
+                } else if escapes.is_empty() {
+                    Tactic::HeadToHead
+                } else if escapes.iter().all(|c| self.is_pinned(target, c, &head)) {
+                    Tactic::WallPin
+                } else if chokepoints.get_pockets(target).iter().any(|pocket| {
+                    pocket.exit == head
+                        && pocket.inside < self.snakes[target].size()
+                        && chokepoints.can_close(distances, index, target, pocket)
+                }) {
+                    Tactic::CutOff
+                } else {
+                    continue;
+                };
+                kills.push(KillMove {
+                    dir,
+                    target,
+                    tactic,
+                });
+            }
+        }
+        kills
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::GameInfo;
+    use super::*;
+
+    fn board(me: &str, other: &str) -> Board {
+        Board::from_api(&GameInfo::new(&format!(
+            r#"{{
+            "game": {{"id": "game-1", "timeout": 500}},
+            "turn": 5,
+            "board": {{
+                "height": 7,
+                "width": 7,
+                "food": [],
+                "hazards": [],
+                "snakes": [
+                    {{"id": "me", "name": "me", "health": 90, "body": [{}], "head": {{"x": 0, "y": 0}}, "length": 4, "shout": ""}},
+                    {{"id": "other", "name": "other", "health": 90, "body": [{}], "head": {{"x": 0, "y": 0}}, "length": 3, "shout": ""}}
+                ]
+            }},
+            "you": {{"id": "me", "name": "me", "health": 90, "body": [{}], "head": {{"x": 0, "y": 0}}, "length": 4, "shout": ""}}
+        }}"#,
+            me, other, me
+        )))
+    }
+
+    fn kills(board: &Board) -> Vec<KillMove> {
+        board.get_kill_moves(
+            &board.get_distance_maps(&[], 1),
+            &Chokepoints::new(board),
+            0,
+        )
+    }
+
+    #[test]
+    fn head_to_head() {
+        // the other snake is in the corner with a single way out
+        let board = board(
+            r#"{"x": 1, "y": 1}, {"x": 2, "y": 1}, {"x": 3, "y": 1}, {"x": 4, "y": 1}"#,
+            r#"{"x": 0, "y": 0}, {"x": 0, "y": 1}, {"x": 0, "y": 2}"#,
+        );
+        assert_eq!(
+            kills(&board),
+            vec![KillMove {
+                dir: Direction::Down,
+                target: 1,
+                tactic: Tactic::HeadToHead
+            }]
+        );
+        // not against a longer snake
+        let mut board = board;
+        board.snakes[0].body.nodes.truncate(2);
+        assert_eq!(kills(&board), vec![]);
+    }
+
+    #[test]
+    fn wall_pin() {
+        // both heading up, the other snake along the wall
+        let board = board(
+            r#"{"x": 1, "y": 3}, {"x": 1, "y": 2}, {"x": 1, "y": 1}, {"x": 1, "y": 0}"#,
+            r#"{"x": 0, "y": 2}, {"x": 0, "y": 1}, {"x": 0, "y": 0}"#,
+        );
+        assert_eq!(
+            kills(&board),
+            vec![
+                KillMove {
+                    dir: Direction::Left,
+                    target: 1,
+                    tactic: Tactic::HeadToHead
+                },
+                KillMove {
+                    dir: Direction::Up,
+                    target: 1,
+                    tactic: Tactic::WallPin
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn cut_off() {
+        // the other snake is in a pocket to the bottom left we close first
+        let board = board(
+            r#"{"x": 1, "y": 3}, {"x": 2, "y": 3}, {"x": 2, "y": 2}, {"x": 2, "y": 1}, {"x": 2, "y": 0}, {"x": 3, "y": 0}"#,
+            r#"{"x": 1, "y": 2}, {"x": 1, "y": 1}, {"x": 1, "y": 0}, {"x": 0, "y": 0}, {"x": 0, "y": 1}"#,
+        );
+        assert_eq!(
+            kills(&board),
+            vec![KillMove {
+                dir: Direction::Left,
+                target: 1,
+                tactic: Tactic::CutOff
+            }]
+        );
+    }
+}
@@ -0,0 +1,202 @@
+//! TD(λ) training of evaluator weights from recorded games
+//!
+//! The evaluation is treated as a linear value function over the component
+//! scores of our snake, `V(s) = Σ w_i f_i(s)`. Weights start from the configured
+//! ones normalized to sum up to 1, which makes `V` equal to the current
+//! evaluation. Each recorded game is replayed with eligibility traces, the
+//! final outcome (win 1.0, draw 0.5, loss 0.0) being the reward of the last position.
+//! Weights are kept non-negative and scaled back to the original total when written out.
+//!
+use rayon::prelude::*;
+
+use super::evaluator::{EvaluatorConfig, Position};
+use super::replay::Replay;
+use super::Board;
+
+#[derive(Debug, Clone)]
+pub struct TdParams {
+    pub lambda: f32,
+    /// learning rate
+    pub alpha: f32,
+    pub epochs: usize,
+}
+
+impl Default for TdParams {
+    fn default() -> Self {
+        Self {
+            lambda: 0.7,
+            alpha: 0.01,
+            epochs: 10,
+        }
+    }
+}
+
+impl TdParams {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.lambda) {
+            return Err(format!("Lambda {} has to be within 0.0 - 1.0", self.lambda));
+        }
+        if !self.alpha.is_finite() || self.alpha <= 0.0 {
+            return Err(format!("Learning rate {} has to be positive", self.alpha));
+        }
+        Ok(())
+    }
+}
+
+/// Features of our positions in a single game and its outcome
+#[derive(Debug, Clone)]
+pub struct Episode {
+    pub features: Vec<Vec<f32>>,
+    pub outcome: f32,
+}
+
+impl Episode {
+    /// Nothing for games without an outcome or moves
+    pub fn from_replay(replay: &Replay, config: &EvaluatorConfig) -> Option<Self> {
+        let outcome = replay.get_outcome()?;
+        let features: Vec<Vec<f32>> = replay
+            .get_moves()
+            .par_iter()
+            .map(|(state, _)| get_features(config, &Board::from_api(state), state.get_hazards()))
+            .collect();
+        if features.is_empty() {
+            return None;
+        }
+        Some(Self { features, outcome })
+    }
+}
+
+/// Component scores of our snake in the order of `config.weights`
+pub fn get_features(config: &EvaluatorConfig, board: &Board, hazards: &[super::Point]) -> Vec<f32> {
+    let position = Position::new(board, hazards, config.hazard_cost);
+    config
+        .weights
+        .keys()
+        .filter_map(|name| config.get_component(name))
+        .map(|component| component.score(&position, 0))
+        .collect()
+}
+
+pub struct TdTrainer {
+    config: EvaluatorConfig,
+    weights: Vec<f32>,
+    params: TdParams,
+}
+
+impl TdTrainer {
+    pub fn new(config: EvaluatorConfig, params: TdParams) -> Self {
+        let total: f32 = config.weights.values().sum();
+        let weights = config.weights.values().map(|w| w / total).collect();
+        Self {
+            config,
+            weights,
+            params,
+        }
+    }
+
+    fn value(&self, features: &[f32]) -> f32 {
+        self.weights
+            .iter()
+            .zip(features.iter())
+            .map(|(w, f)| w * f)
+            .sum()
+    }
+
+    /// Single pass over the game, returns the mean squared TD error
+    pub fn train_episode(&mut self, episode: &Episode) -> f32 {
+        let mut trace = vec![0.0; self.weights.len()];
+        let mut error = 0.0;
+        let n = episode.features.len();
+        for t in 0..n {
+            let target = match episode.features.get(t + 1) {
+                Some(next) => self.value(next),
+                None => episode.outcome,
+            };
+            let delta = target - self.value(&episode.features[t]);
+            error += delta * delta;
+            for (e, f) in trace.iter_mut().zip(episode.features[t].iter()) {
+                *e = self.params.lambda * *e + f;
+            }
+            for (w, e) in self.weights.iter_mut().zip(trace.iter()) {
+                *w = (*w + self.params.alpha * delta * e).max(0.0);
+            }
+        }
+        error / n as f32
+    }
+
+    /// Runs all epochs, returns mean TD error of each
+    pub fn train(&mut self, episodes: &[Episode]) -> Vec<f32> {
+        (0..self.params.epochs)
+            .map(|_| {
+                let total: f32 = episodes.iter().map(|e| self.train_episode(e)).sum();
+                total / episodes.len().max(1) as f32
+            })
+            .collect()
+    }
+
+    /// Config with the learned weights, scaled to the total of the original weights
+    pub fn get_config(&self) -> EvaluatorConfig {
+        let mut config = self.config.clone();
+        let total: f32 = config.weights.values().sum();
+        let learned: f32 = self.weights.iter().sum();
+        if learned > 0.0 {
+            for (w, learned_w) in config.weights.values_mut().zip(self.weights.iter()) {
+                *w = learned_w * total / learned;
+            }
+        }
+        config
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn learns_from_outcomes() {
+        let weights: BTreeMap<String, f32> =
+            vec![("area".to_string(), 1.0), ("length".to_string(), 1.0)]
+                .into_iter()
+                .collect();
+        let config = EvaluatorConfig {
+            weights,
+            ..EvaluatorConfig::default()
+        };
+        // area is high in won games, length doesn't matter
+        let episodes: Vec<Episode> = (0..20)
+            .map(|i| {
+                let win = i % 2 == 0;
+                let area = if win { 0.9 } else { 0.1 };
+                Episode {
+                    features: vec![vec![area, 0.5]; 5],
+                    outcome: if win { 1.0 } else { 0.0 },
+                }
+            })
+            .collect();
+        let mut trainer = TdTrainer::new(
+            config,
+            TdParams {
+                epochs: 30,
+                alpha: 0.05,
+                ..TdParams::default()
+            },
+        );
+        let errors = trainer.train(&episodes);
+        assert!(errors.last().unwrap() < errors.first().unwrap());
+        let learned = trainer.get_config();
+        assert!(learned.weights["area"] > learned.weights["length"]);
+        assert!((learned.weights.values().sum::<f32>() - 2.0).abs() < 1e-4);
+        assert!(learned.validate().is_ok());
+
+        assert!(TdParams::default().validate().is_ok());
+        for (lambda, alpha) in [(1.5, 0.01), (-0.1, 0.01), (0.7, 0.0), (0.7, f32::NAN)].iter() {
+            let params = TdParams {
+                lambda: *lambda,
+                alpha: *alpha,
+                ..TdParams::default()
+            };
+            assert!(params.validate().is_err(), "{} {}", lambda, alpha);
+        }
+    }
+}
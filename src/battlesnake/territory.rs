@@ -0,0 +1,198 @@
+//! Voronoi territory control
+//!
+//! Built from the distance maps of all snake heads. Each cell belongs to the
+//! snake that reaches it first, ties go to the longer snake and cells reached
+//! at the same time by snakes of equal length stay contested.
+//! Body cells are entered only once vacated, same as in the flood fill.
+//!
+use std::fmt;
+
+use super::distance::DistanceMaps;
+use super::Board;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cell {
+    Free,
+    Owned(usize),
+    Contested,
+}
+
+#[derive(Debug, Clone)]
+pub struct Territory {
+    pub cells: Vec<Cell>,
+    /// Number of cells owned by each snake, heads excluded
+    pub size: Vec<usize>,
+    /// Number of food in each snake's territory
+    pub food: Vec<usize>,
+    /// Owned cells neighbouring a cell owned by another snake or a contested one
+    pub border: Vec<usize>,
+    width: usize,
+}
+
+impl Territory {
+    /// Share of all claimed cells owned by snake `index`
+    pub fn get_share(&self, index: usize) -> f32 {
+        let total: usize = self.size.iter().sum();
+        if total == 0 {
+            return 0.0;
+        }
+        self.size[index] as f32 / total as f32
+    }
+}
+
+impl Territory {
+    pub fn from_distances(board: &Board, distances: &DistanceMaps) -> Self {
+        let n_snakes = board.snakes.len();
+        let area = board.get_width() * board.get_height();
+        let mut cells = vec![Cell::Free; area];
+        let mut size = vec![0; n_snakes];
+        let mut border = vec![0; n_snakes];
+        for (c, cell) in cells.iter_mut().enumerate() {
+            let (owner, steps) = match Self::get_owner(board, distances, c) {
+                (Cell::Owned(owner), steps) => (owner, steps),
+                (other, _) => {
+                    *cell = other;
+                    continue;
+                }
+            };
+            *cell = Cell::Owned(owner);
+            if steps > 0 {
+                size[owner] += 1;
+            }
+            // neighbours may not be assigned yet, resolve their owners directly
+            let is_border = board.get_point(c).get_neighbours().iter().any(|n| {
+                board.is_inbounds(n)
+                    && match Self::get_owner(board, distances, board.get_index(n)).0 {
+                        Cell::Owned(other) => other != owner,
+                        Cell::Contested => true,
+                        Cell::Free => false,
+                    }
+            });
+            if is_border {
+                border[owner] += 1;
+            }
+        }
+
+        let mut food = vec![0; n_snakes];
+        for f in board.food.iter() {
+            if board.is_inbounds(f) {
+                if let Cell::Owned(owner) = cells[board.get_index(f)] {
+                    food[owner] += 1;
+                }
+            }
+        }
+
+        Territory {
+            cells,
+            size,
+            food,
+            border,
+            width: board.get_width(),
+        }
+    }
+
+    /// Owner of cell `c` and the steps it needs to get there
+    fn get_owner(board: &Board, distances: &DistanceMaps, c: usize) -> (Cell, usize) {
+        // (owner, owner's size, steps, tie)
+        let mut best: Option<(usize, usize, usize, bool)> = None;
+        for (i, snake) in board.snakes.iter().enumerate() {
+            let steps = match distances.get_steps_map(i)[c] {
+                Some(steps) => steps,
+                None => continue,
+            };
+            best = match best {
+                Some((_, _, s, _)) if steps > s => best,
+                Some((owner, owner_size, s, _)) if steps == s && snake.size() == owner_size => {
+                    Some((owner, owner_size, s, true))
+                }
+                Some((_, owner_size, s, _)) if steps == s && snake.size() < owner_size => best,
+                _ => Some((i, snake.size(), steps, false)),
+            };
+        }
+        match best {
+            Some((_, _, steps, true)) => (Cell::Contested, steps),
+            Some((owner, _, steps, false)) => (Cell::Owned(owner), steps),
+            None => (Cell::Free, 0),
+        }
+    }
+}
+
+impl fmt::Display for Territory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.cells.chunks(self.width).rev() {
+            for cell in row.iter() {
+                match cell {
+                    Cell::Free => write!(f, "◦")?,
+                    Cell::Contested => write!(f, "~")?,
+                    Cell::Owned(i) if *i < 10 => write!(f, "{}", i)?,
+                    Cell::Owned(_) => write!(f, "S")?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{GameInfo, Point};
+    use super::*;
+
+    fn two_snakes(body: &str, other: &str, food: &str) -> Board {
+        Board::from_api(&GameInfo::new(&format!(
+            r#"{{
+            "game": {{"id": "game-1", "timeout": 500}},
+            "turn": 5,
+            "board": {{
+                "height": 5,
+                "width": 5,
+                "food": [{}],
+                "hazards": [],
+                "snakes": [
+                    {{"id": "me", "name": "me", "health": 90, "body": [{}], "head": {{"x": 0, "y": 0}}, "length": 0, "shout": ""}},
+                    {{"id": "other", "name": "other", "health": 90, "body": [{}], "head": {{"x": 0, "y": 0}}, "length": 0, "shout": ""}}
+                ]
+            }},
+            "you": {{"id": "me", "name": "me", "health": 90, "body": [{}], "head": {{"x": 0, "y": 0}}, "length": 0, "shout": ""}}
+        }}"#,
+            food, body, other, body
+        )))
+    }
+
+    #[test]
+    fn split_equal() {
+        // equal snakes in opposite corners split the board along the diagonal
+        let board = two_snakes(
+            r#"{"x": 0, "y": 0}, {"x": 0, "y": 0}"#,
+            r#"{"x": 4, "y": 4}, {"x": 4, "y": 4}"#,
+            r#"{"x": 1, "y": 1}, {"x": 3, "y": 4}, {"x": 4, "y": 0}"#,
+        );
+        let territory = Territory::from_distances(&board, &board.get_distance_maps(&[], 1));
+        assert_eq!(territory.size, vec![9, 9]);
+        assert_eq!(territory.food, vec![1, 1]);
+        // the cells next to the contested diagonal, heads excluded
+        assert_eq!(territory.border, vec![4, 4]);
+        assert_eq!(
+            territory.cells[board.get_index(&Point::new(4, 0))],
+            Cell::Contested
+        );
+        assert!((territory.get_share(0) - 0.5).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn longer_wins_ties() {
+        let board = two_snakes(
+            r#"{"x": 0, "y": 0}, {"x": 0, "y": 0}, {"x": 0, "y": 0}"#,
+            r#"{"x": 4, "y": 4}, {"x": 4, "y": 4}"#,
+            "",
+        );
+        let territory = Territory::from_distances(&board, &board.get_distance_maps(&[], 1));
+        assert_eq!(territory.size, vec![14, 9]);
+        assert_eq!(territory.border, vec![5, 4]);
+        assert_eq!(
+            territory.cells[board.get_index(&Point::new(4, 0))],
+            Cell::Owned(0)
+        );
+    }
+}
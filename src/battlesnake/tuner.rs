@@ -0,0 +1,195 @@
+//! Evaluator weight tuning by self-play
+//!
+//! Uses simultaneous perturbation stochastic approximation (SPSA). Every
+//! iteration nudges all weights at once in a random direction, plays the
+//! `+` and `-` perturbed evaluators against each other in the arena and
+//! moves the weights towards the one that won more. It needs only a single
+//! batch of games per iteration regardless of the number of weights, which
+//! keeps the noisy win rate estimates affordable.
+//!
+//! The seed fixes the perturbations and the starting positions, but moves are
+//! searched on a time budget, so two runs with the same seed can still differ.
+//!
+use log::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+use super::arena::{play_game, ArenaParams, Contestant};
+use super::config::Settings;
+
+#[derive(Debug, Clone)]
+pub struct TunerParams {
+    pub iterations: usize,
+    /// games played per iteration
+    pub games: usize,
+    /// step size
+    pub a: f32,
+    /// perturbation size
+    pub c: f32,
+    pub seed: u64,
+    pub arena: ArenaParams,
+}
+
+impl Default for TunerParams {
+    fn default() -> Self {
+        Self {
+            iterations: 50,
+            games: 20,
+            a: 0.2,
+            c: 0.2,
+            seed: 0,
+            arena: ArenaParams::default(),
+        }
+    }
+}
+
+pub struct Tuner {
+    params: TunerParams,
+    base: Settings,
+    names: Vec<String>,
+    theta: Vec<f32>,
+    rng: StdRng,
+}
+
+impl Tuner {
+    /// Tunes weights of the components listed in `base` evaluator
+    pub fn new(base: Settings, params: TunerParams) -> Self {
+        let names = base.evaluator.weights.keys().cloned().collect();
+        let theta = base.evaluator.weights.values().cloned().collect();
+        let rng = StdRng::seed_from_u64(params.seed);
+        Self {
+            params,
+            base,
+            names,
+            theta,
+            rng,
+        }
+    }
+
+    /// Base settings with weights `theta`
+    fn get_settings_for(&self, theta: &[f32]) -> Settings {
+        let mut settings = self.base.clone();
+        settings.evaluator.weights = self
+            .names
+            .iter()
+            .cloned()
+            .zip(theta.iter().cloned())
+            .collect();
+        settings
+    }
+
+    pub fn get_settings(&self) -> Settings {
+        self.get_settings_for(&self.theta)
+    }
+
+    /// Single SPSA iteration, returns win rate of the `+` perturbation
+    pub fn step(&mut self, k: usize) -> f32 {
+        let stability = self.params.iterations as f32 / 10.0;
+        let a_k = self.params.a / (k as f32 + 1.0 + stability).powf(0.602);
+        let c_k = self.params.c / (k as f32 + 1.0).powf(0.101);
+        let delta: Vec<f32> = (0..self.theta.len())
+            .map(|_| if self.rng.gen::<bool>() { 1.0 } else { -1.0 })
+            .collect();
+        let perturb = |sign: f32| -> Vec<f32> {
+            self.theta
+                .iter()
+                .zip(delta.iter())
+                .map(|(t, d)| (t + sign * c_k * d).max(0.0))
+                .collect()
+        };
+        let plus = self.get_settings_for(&perturb(1.0));
+        let minus = self.get_settings_for(&perturb(-1.0));
+        let seed = self.rng.gen::<u64>();
+        let win_rate = duel(&plus, &minus, self.params.games, &self.params.arena, seed);
+
+        // (y+ - y-) / (2 c_k delta) with y- = 1 - y+
+        let total: f32 = self.theta.iter().sum();
+        for (t, d) in self.theta.iter_mut().zip(delta.iter()) {
+            *t = (*t + a_k * (win_rate - 0.5) / (c_k * d)).max(0.0);
+        }
+        // evaluation is a weighted average, keep the scale from drifting
+        let new_total: f32 = self.theta.iter().sum();
+        if new_total > 0.0 {
+            self.theta.iter_mut().for_each(|t| *t *= total / new_total);
+        }
+        win_rate
+    }
+
+    /// Runs all iterations and returns the tuned settings
+    pub fn run(&mut self) -> Settings {
+        for k in 0..self.params.iterations {
+            let win_rate = self.step(k);
+            info!(
+                "Tuner iteration {}/{}: win rate of + {:.2}, weights {}",
+                k + 1,
+                self.params.iterations,
+                win_rate,
+                self.get_settings().evaluator.build()
+            );
+        }
+        self.get_settings()
+    }
+}
+
+/// Win rate of `a` against `b`, draws count as half a win.
+/// Games run in parallel and alternate the contestants' order.
+pub fn duel(a: &Settings, b: &Settings, games: usize, arena: &ArenaParams, seed: u64) -> f32 {
+    if games == 0 {
+        return 0.5;
+    }
+    let score: f32 = (0..games)
+        .into_par_iter()
+        .map(|g| {
+            let swapped = g % 2 == 1;
+            let contestants = if swapped {
+                [
+                    Contestant::new("b", b.clone()),
+                    Contestant::new("a", a.clone()),
+                ]
+            } else {
+                [
+                    Contestant::new("a", a.clone()),
+                    Contestant::new("b", b.clone()),
+                ]
+            };
+            match play_game(&contestants, arena, seed.wrapping_add(g as u64)).winner {
+                Some(w) if (w == 0) != swapped => 1.0,
+                Some(_) => 0.0,
+                None => 0.5,
+            }
+        })
+        .sum();
+    score / games as f32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn spsa() {
+        let params = TunerParams {
+            iterations: 2,
+            games: 2,
+            arena: ArenaParams {
+                width: 7,
+                height: 7,
+                max_turns: 15,
+                time_budget: Duration::from_millis(2),
+                ..ArenaParams::default()
+            },
+            ..TunerParams::default()
+        };
+        let base = Settings::default();
+        let total: f32 = base.evaluator.weights.values().sum();
+        let mut tuner = Tuner::new(base, params);
+        let tuned = tuner.run();
+        assert_eq!(tuned.evaluator.weights.len(), 7);
+        assert!(tuned.evaluator.weights.values().all(|w| *w >= 0.0));
+        let new_total: f32 = tuned.evaluator.weights.values().sum();
+        assert!((new_total - total).abs() < 1e-3);
+        assert!(tuned.validate().is_ok());
+    }
+}
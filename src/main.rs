@@ -7,72 +7,207 @@ extern crate lazy_static;
 
 // Depts
 use actix_web::{get, post, web, App, HttpResponse, HttpServer, Responder};
-use clap::{App as ClApp, Arg as ClArg};
+use clap::{App as ClApp, Arg as ClArg, ArgMatches, SubCommand};
 use log::*;
 
 // Std
+use std::fs;
 use std::io;
 use std::time::{Duration, SystemTime};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Mutex;
+use std::sync::{Mutex, RwLock};
+use std::thread;
 
 // Battlesnake
 mod battlesnake;
 use battlesnake::init_logger;
+use battlesnake::GameRegistry;
 use battlesnake::SessionStats;
-use battlesnake::SnakeProps;
-use battlesnake::get_move;
+use battlesnake::config::{Config, ConfigStore, Settings};
+use battlesnake::dataset::Dataset;
+use battlesnake::Evaluator;
+use battlesnake::replay::{Recorder, Replay, ReplayRecord};
+use battlesnake::strategy::validate_move;
+use battlesnake::td::{Episode, TdParams, TdTrainer};
+use battlesnake::tuner::{Tuner, TunerParams};
 use battlesnake::GameInfo;
+use battlesnake::arena::{play_game_recorded, ArenaParams, Contestant, MAX_SNAKES};
+#[cfg(feature = "nn")]
+use battlesnake::nn::{Network, NnEvaluator};
+use std::sync::Arc;
 
 // Vars
 static TIME_BUDGET: AtomicU64 = AtomicU64::new(280);
 lazy_static! {
     static ref SESSION_STATS: Mutex<SessionStats> = Mutex::new(SessionStats::new(1200));
+    static ref CONFIG: ConfigStore = ConfigStore::new();
+    static ref RECORDER: RwLock<Option<Recorder>> = RwLock::new(None);
+}
+#[cfg(feature = "nn")]
+lazy_static! {
+    static ref NETWORK: RwLock<Option<Arc<Network>>> = RwLock::new(None);
+}
+
+/// Snake served at the root or under the URL prefix of a persona
+struct Mount {
+    persona: Option<String>,
+    /// games of this snake, kept apart as personas may play the same game
+    registry: Mutex<GameRegistry>,
+}
+
+impl Mount {
+    fn new(persona: Option<String>, game_timeout: u64) -> Self {
+        Self {
+            persona,
+            registry: Mutex::new(GameRegistry::new(game_timeout)),
+        }
+    }
+
+    /// Active config of the snake, the persona's from the latest config
+    fn get_config(&self) -> Arc<Config> {
+        let config = CONFIG.get();
+        match self.persona.as_ref() {
+            Some(name) => match config.get_persona(name) {
+                Some(persona) => persona.config.clone(),
+                None => {
+                    warn!("Persona {} is gone from the config, using the defaults", name);
+                    config
+                }
+            },
+            None => config,
+        }
+    }
+
+    fn get_time_budget(&self) -> Duration {
+        let budget = self
+            .persona
+            .as_ref()
+            .and_then(|name| CONFIG.get().get_persona(name).and_then(|p| p.time_budget));
+        Duration::from_millis(budget.unwrap_or_else(|| TIME_BUDGET.load(Ordering::SeqCst)))
+    }
+
+    /// Id of the game in stats and replays, personas may play the same game
+    /// so theirs get the persona name appended
+    fn get_game_id(&self, gameinfo: &GameInfo) -> String {
+        match self.persona.as_ref() {
+            Some(name) => format!("{}-{}", gameinfo.get_game_id(), name),
+            None => gameinfo.get_game_id(),
+        }
+    }
+
+    fn get_prefix(&self) -> String {
+        self.persona.as_ref().map(|name| format!("/{}", name)).unwrap_or_default()
+    }
+}
+
+/// Appends to the game's replay if recording is enabled
+fn record(record: ReplayRecord) {
+    if let Some(recorder) = RECORDER.read().unwrap().as_ref() {
+        if let Err(e) = recorder.record(&record) {
+            warn!("{}", e);
+        }
+    }
+}
+
+/// Evaluator of the settings, replaced by the network if one is loaded
+fn get_evaluator(settings: &Settings) -> Box<dyn Evaluator> {
+    let evaluator = settings.evaluator.build();
+    #[cfg(feature = "nn")]
+    if let Some(network) = NETWORK.read().unwrap().clone() {
+        return Box::new(NnEvaluator::new(network, evaluator));
+    }
+    Box::new(evaluator)
 }
 
 #[get("/")]
-async fn index() -> impl Responder {
+async fn index(mount: web::Data<Mount>) -> impl Responder {
     debug!("Received Index");
-    let snake = SnakeProps::new();
+    let config = mount.get_config();
     // let datastr = snake.get_string();
-    HttpResponse::Ok().body(snake.get_string())
+    HttpResponse::Ok().body(config.get_snake().get_string())
 }
 
 #[post("/move")]
-async fn domove(data: String) -> impl Responder {
+async fn domove(mount: web::Data<Mount>, data: String) -> impl Responder {
     let start_time = SystemTime::now();
     let game_data = GameInfo::new(&data);
+    let game_id = mount.get_game_id(&game_data);
     let mut session_stats = SESSION_STATS.lock().unwrap();
-    session_stats.update_game(&game_data.get_game_id(), game_data.get_turn() as usize);
-    let movement = get_move(&game_data, Duration::from_millis(TIME_BUDGET.load(Ordering::SeqCst)));
+    session_stats.update_game(&game_id, game_data.get_turn() as usize);
+    std::mem::drop(session_stats);
+    let game_ctx = mount.registry.lock().unwrap().get_game(&game_data, mount.get_config());
+    let mut ctx = game_ctx.lock().unwrap();
+    ctx.observe(&game_data);
+    let config = ctx.config.clone();
+    let settings = config.get(game_data.get_ruleset());
+    let evaluator = get_evaluator(settings);
+    let strategy = settings.strategy.build();
+    let movement = strategy.get_move(&game_data, &mut ctx, mount.get_time_budget(), settings, &*evaluator);
+    let movement = validate_move(&game_data, &mut ctx, settings, movement);
+    if let Some(search) = ctx.search.as_ref() {
+        debug!("{}", search);
+    }
+    let mut state = game_data;
+    state.game.id = game_id;
+    record(ReplayRecord::Move {
+        chosen: movement.movement,
+        scores: ctx.search.as_ref().map(|s| s.root_scores).unwrap_or_default(),
+        state,
+    });
     let duration = SystemTime::now()
         .duration_since(start_time)
         .unwrap()
         .as_millis();
-    info!("Handled /move [{}] in {}ms", movement.movement, duration);
+    info!("Handled {}/move [{}] in {}ms", mount.get_prefix(), movement.movement, duration);
     HttpResponse::Ok().body(movement.get_json_string())
 }
 
 #[post("/start")]
-async fn start(data: String) -> impl Responder {
+async fn start(mount: web::Data<Mount>, data: String) -> impl Responder {
     debug!("Received START");
     let game_data = GameInfo::new(&data);
     let mut session_stats = SESSION_STATS.lock().unwrap();
     session_stats.garbage_collect();
-    session_stats.start_game(game_data.get_game_id());
+    session_stats.start_game(mount.get_game_id(&game_data));
     debug!("{}", session_stats);
+    std::mem::drop(session_stats);
+    let mut registry = mount.registry.lock().unwrap();
+    registry.garbage_collect();
+    let game_ctx = registry.start_game(&game_data, mount.get_config());
+    debug!("{}", registry);
+    std::mem::drop(registry);
+    let mut ctx = game_ctx.lock().unwrap();
+    let config = ctx.config.clone();
+    let settings = config.get(game_data.get_ruleset());
+    info!("Playing {} with the {} strategy", ctx.id, settings.strategy);
+    settings.strategy.build().start(&game_data, &mut ctx);
     HttpResponse::Ok()
 }
 
 #[post("/end")]
-async fn end(data: String) -> impl Responder {
+async fn end(mount: web::Data<Mount>, data: String) -> impl Responder {
     debug!("Received END");
     let game_data = GameInfo::new(&data);
     let win = true; // Assuming for now that if received end, we won else fix needed in game_data.is_win();
+    let game_id = mount.get_game_id(&game_data);
     let mut session_stats = SESSION_STATS.lock().unwrap();
-    session_stats.end_game(&game_data.get_game_id(), win);
+    session_stats.end_game(&game_id, win);
     session_stats.garbage_collect();
     debug!("{}", session_stats);
+    std::mem::drop(session_stats);
+    let mut registry = mount.registry.lock().unwrap();
+    if let Some(game_ctx) = registry.end_game(&game_data.get_game_id()) {
+        let mut ctx = game_ctx.lock().unwrap();
+        debug!("Game {} lasted {}s", ctx.id, ctx.get_duration().as_secs());
+        let config = ctx.config.clone();
+        config.get(game_data.get_ruleset()).strategy.build().end(&game_data, &mut ctx);
+    }
+    registry.garbage_collect();
+    debug!("{}", registry);
+    std::mem::drop(registry);
+    let mut state = game_data;
+    state.game.id = game_id;
+    record(ReplayRecord::End { state });
     HttpResponse::Ok()
 }
 
@@ -82,7 +217,7 @@ async fn main() -> io::Result<()> {
     init_logger();
 
     // Process arguments
-    let arguments = ClApp::new("battlesnake")
+    let app = ClApp::new("battlesnake")
     .author("hello@jiricodes.com")
     .version(crate_version!())
     .arg(
@@ -107,8 +242,178 @@ async fn main() -> io::Result<()> {
         .short("s")
         .long("stats-game-timeout")
         .takes_value(true)
-        .help("Sets timeout in seconds for session statistics' games. This is required due to battlesnake API invoking game end when not winning snake. Default 1200s (20 minutes)")
-    ).get_matches();
+        .help("Sets timeout in seconds for session statistics' games and per-game state. This is required due to battlesnake API invoking game end when not winning snake. Default 1200s (20 minutes)")
+    ).arg(
+        ClArg::with_name("search")
+        .long("search")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .help("Search mode and opponent move policy, optionally per ruleset, e.g. \"royale=mixed:0.5/learned\". Modes: paranoid, expectimax, mixed:<alpha>. Policies: uniform, softmax:<temperature>, learned. Default paranoid/learned. Overrides the config file")
+    ).arg(
+        ClArg::with_name("strategy")
+        .long("strategy")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .help("Strategy picking the moves, optionally per ruleset, e.g. \"solo=astar\". Strategies: minimax, astar. Default minimax. Overrides the config file")
+    ).arg(
+        ClArg::with_name("snake")
+        .long("snake")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .help("Snake customization answered on /, optionally per persona, e.g. \"color=#622BAA\" or \"aggro.head=evil\". Fields: author, color, head, tail, version. Overrides the config file")
+    ).arg(
+        ClArg::with_name("config")
+        .short("c")
+        .long("config")
+        .takes_value(true)
+        .help("JSON config file with evaluator weights and search parameters, optionally per ruleset")
+    ).arg(
+        ClArg::with_name("watch_config")
+        .long("watch-config")
+        .takes_value(true)
+        .requires("config")
+        .help("Checks the config file for changes every given number of seconds and reloads it. New config applies to games started after the reload")
+    ).arg(
+        ClArg::with_name("record_dir")
+        .long("record-dir")
+        .takes_value(true)
+        .help("Records every game as <game id>.jsonl in the directory, <game id>-<persona>.jsonl for personas")
+    ).subcommand(
+        SubCommand::with_name("selfplay")
+        .about("Plays games of the default config against itself and records them from every snake's view as training data")
+        .arg(
+            ClArg::with_name("output")
+            .short("o")
+            .long("output")
+            .takes_value(true)
+            .required(true)
+            .help("Directory to record the games to")
+        ).arg(
+            ClArg::with_name("games")
+            .long("games")
+            .takes_value(true)
+            .help("Number of games. Default 100")
+        ).arg(
+            ClArg::with_name("snakes")
+            .long("snakes")
+            .takes_value(true)
+            .help("Snakes per game, 1 - 8. Default 2")
+        ).arg(
+            ClArg::with_name("move_time")
+            .long("move-time")
+            .takes_value(true)
+            .help("Search time per move in ms. Default 20")
+        ).arg(
+            ClArg::with_name("seed")
+            .long("seed")
+            .takes_value(true)
+            .help("Random seed of the first game. Default 0")
+        )
+    ).subcommand(
+        SubCommand::with_name("export-dataset")
+        .about("Converts recorded games into NPY training data: board planes, legal moves, chosen move, search scores and outcome")
+        .arg(
+            ClArg::with_name("replays")
+            .short("r")
+            .long("replays")
+            .takes_value(true)
+            .multiple(true)
+            .required(true)
+            .help("Directories with recorded games, from the server's --record-dir or selfplay")
+        ).arg(
+            ClArg::with_name("output")
+            .short("o")
+            .long("output")
+            .takes_value(true)
+            .required(true)
+            .help("Directory to write the NPY files to")
+        ).arg(
+            ClArg::with_name("width")
+            .long("width")
+            .takes_value(true)
+            .help("Board width, games on other boards are skipped. Default 11")
+        ).arg(
+            ClArg::with_name("height")
+            .long("height")
+            .takes_value(true)
+            .help("Board height, games on other boards are skipped. Default 11")
+        )
+    ).subcommand(
+        SubCommand::with_name("tune")
+        .about("Tunes evaluator weights of the default config by self-play and writes the result as a config file. Rulesets and personas keep the values they override")
+        .arg(
+            ClArg::with_name("output")
+            .short("o")
+            .long("output")
+            .takes_value(true)
+            .required(true)
+            .help("Config file to write the tuned weights to")
+        ).arg(
+            ClArg::with_name("iterations")
+            .long("iterations")
+            .takes_value(true)
+            .help("Number of tuning iterations. Default 50")
+        ).arg(
+            ClArg::with_name("games")
+            .long("games")
+            .takes_value(true)
+            .help("Self-play games per iteration. Default 20")
+        ).arg(
+            ClArg::with_name("move_time")
+            .long("move-time")
+            .takes_value(true)
+            .help("Search time per move in ms. Default 20")
+        ).arg(
+            ClArg::with_name("seed")
+            .long("seed")
+            .takes_value(true)
+            .help("Random seed of the perturbations and games. Moves are searched on the time budget, so runs with the same seed can differ. Default 0")
+        )
+    ).subcommand(
+        SubCommand::with_name("train-td")
+        .about("Fits evaluator weights of the default config to recorded games by TD(lambda) and writes the result as a config file")
+        .arg(
+            ClArg::with_name("replays")
+            .short("r")
+            .long("replays")
+            .takes_value(true)
+            .required(true)
+            .help("Directory with recorded games")
+        ).arg(
+            ClArg::with_name("output")
+            .short("o")
+            .long("output")
+            .takes_value(true)
+            .required(true)
+            .help("Config file to write the learned weights to")
+        ).arg(
+            ClArg::with_name("lambda")
+            .long("lambda")
+            .takes_value(true)
+            .help("Trace decay. Default 0.7")
+        ).arg(
+            ClArg::with_name("alpha")
+            .long("alpha")
+            .takes_value(true)
+            .help("Learning rate. Default 0.01")
+        ).arg(
+            ClArg::with_name("epochs")
+            .long("epochs")
+            .takes_value(true)
+            .help("Passes over the recorded games. Default 10")
+        )
+    );
+    #[cfg(feature = "nn")]
+    let app = app.arg(
+        ClArg::with_name("network")
+        .long("network")
+        .takes_value(true)
+        .help("JSON weights of a network to evaluate boards of its size with, other boards use the config's evaluator")
+    );
+    let arguments = app.get_matches();
 
     // Set Time Budget if argument passed
     if let Ok(time_budget) = value_t!(arguments, "time_budget", u64) {
@@ -116,26 +421,237 @@ async fn main() -> io::Result<()> {
         info!("Time budget set to {} ms.", TIME_BUDGET.load(Ordering::SeqCst));
     }
     // Set Stats timeout
-    if let Ok(stats_timeout) = value_t!(arguments, "stats_game_timeout", u64) {
+    let game_timeout = value_t!(arguments, "stats_game_timeout", u64).unwrap_or(1200);
+    if arguments.is_present("stats_game_timeout") {
         let mut session_stats = SESSION_STATS.lock().unwrap();
-        session_stats.set_timeout(Duration::from_secs(stats_timeout));
+        session_stats.set_timeout(Duration::from_secs(game_timeout));
         info!("Stats Timeout set to {} seconds", session_stats.get_timeout());
-        std::mem::drop(session_stats);
+    }
+
+    // Load config and set search params per ruleset
+    let search: Vec<String> = arguments
+        .values_of("search")
+        .map(|values| values.map(|v| v.to_string()).collect())
+        .unwrap_or_default();
+    let strategy: Vec<String> = arguments
+        .values_of("strategy")
+        .map(|values| values.map(|v| v.to_string()).collect())
+        .unwrap_or_default();
+    let snake: Vec<String> = arguments
+        .values_of("snake")
+        .map(|values| values.map(|v| v.to_string()).collect())
+        .unwrap_or_default();
+    match CONFIG.load(arguments.value_of("config"), search, strategy, snake) {
+        Ok(config) => info!("Config:\n{}", config),
+        Err(e) => {
+            error!("{}", e);
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+        }
+    }
+    if let Some(selfplay_arguments) = arguments.subcommand_matches("selfplay") {
+        return selfplay(selfplay_arguments);
+    }
+    if let Some(export_arguments) = arguments.subcommand_matches("export-dataset") {
+        return export_dataset(export_arguments);
+    }
+    if let Some(tune_arguments) = arguments.subcommand_matches("tune") {
+        return tune(tune_arguments);
+    }
+    if let Some(train_arguments) = arguments.subcommand_matches("train-td") {
+        return train_td(train_arguments);
+    }
+
+    // Load network evaluator
+    #[cfg(feature = "nn")]
+    if let Some(path) = arguments.value_of("network") {
+        match Network::from_file(path) {
+            Ok(network) => *NETWORK.write().unwrap() = Some(Arc::new(network)),
+            Err(e) => {
+                error!("{}", e);
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+            }
+        }
+        info!("Evaluating by network {}", path);
+    }
+
+    // Record games
+    if let Some(dir) = arguments.value_of("record_dir") {
+        match Recorder::new(dir) {
+            Ok(recorder) => *RECORDER.write().unwrap() = Some(recorder),
+            Err(e) => {
+                error!("{}", e);
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+            }
+        }
+        info!("Recording games to {}", dir);
+    }
+
+    // Watch config file for changes
+    if arguments.is_present("watch_config") {
+        let interval = value_t!(arguments, "watch_config", u64).map_err(|e| {
+            error!("{}", e);
+            io::Error::new(io::ErrorKind::InvalidInput, e.to_string())
+        })?;
+        info!("Watching {} every {}s", CONFIG.get_path().unwrap_or_default(), interval);
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_secs(interval.max(1)));
+            match CONFIG.reload() {
+                Ok(Some(config)) => info!("Config reloaded:\n{}", config),
+                Ok(None) => (),
+                Err(e) => error!("{}. Keeping the current config", e),
+            }
+        });
     }
 
     // Prep IP and Port
     let ip_address = arguments.value_of("ip_address").unwrap_or("0.0.0.0");
     let port = arguments.value_of("port").unwrap_or("6969");
     let address = format!("{}:{}", ip_address, port);
+    // Mount the snake at the root and the personas under their names
+    let root = web::Data::new(Mount::new(None, game_timeout));
+    let personas: Vec<web::Data<Mount>> = CONFIG
+        .get()
+        .get_personas()
+        .keys()
+        .map(|name| web::Data::new(Mount::new(Some(name.clone()), game_timeout)))
+        .collect();
+    for mount in personas.iter() {
+        info!("Serving persona at {}/", mount.get_prefix());
+    }
     // Start the HTTP server
-    HttpServer::new(|| {
-        App::new()
+    HttpServer::new(move || {
+        let mut app = App::new()
+            .app_data(root.clone())
             .service(index)
             .service(domove)
             .service(start)
-            .service(end)
+            .service(end);
+        for mount in personas.iter() {
+            app = app.service(
+                web::scope(&mount.get_prefix())
+                    .app_data(mount.clone())
+                    .service(index)
+                    .service(domove)
+                    .service(start)
+                    .service(end),
+            );
+        }
+        app
     })
     .bind(&address)?
     .run()
     .await
 }
+
+/// Records self-play games of the active config
+fn selfplay(arguments: &ArgMatches) -> io::Result<()> {
+    let games = value_t!(arguments, "games", u64).unwrap_or(100);
+    let snakes = value_t!(arguments, "snakes", usize).unwrap_or(2);
+    if !(1..=MAX_SNAKES).contains(&snakes) {
+        let e = format!("Snakes per game have to be within 1 - {}", MAX_SNAKES);
+        error!("{}", e);
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+    }
+    let seed = value_t!(arguments, "seed", u64).unwrap_or(0);
+    let mut params = ArenaParams::default();
+    if let Ok(move_time) = value_t!(arguments, "move_time", u64) {
+        params.time_budget = Duration::from_millis(move_time);
+    }
+    let output = arguments.value_of("output").unwrap();
+    let recorder = Recorder::new(output).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let settings = CONFIG.get().get_default().clone();
+    let contestants: Vec<Contestant> = (0..snakes)
+        .map(|i| Contestant::new(&format!("self {}", i), settings.clone()))
+        .collect();
+    for game in seed..seed + games {
+        let result = play_game_recorded(&contestants, &params, game, Some(&recorder));
+        info!("Game {}: winner {:?} after {} turns", game, result.winner, result.turns);
+    }
+    info!("Recorded {} games to {}", games, output);
+    Ok(())
+}
+
+/// Writes recorded games as training data
+fn export_dataset(arguments: &ArgMatches) -> io::Result<()> {
+    let width = value_t!(arguments, "width", usize).unwrap_or(11);
+    let height = value_t!(arguments, "height", usize).unwrap_or(11);
+    let mut dataset = Dataset::new(width, height);
+    for dir in arguments.values_of("replays").unwrap() {
+        let replays = Replay::load_dir(dir).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let samples: usize = replays.iter().map(|replay| dataset.add_replay(replay)).sum();
+        info!("{} samples from {} games in {}", samples, replays.len(), dir);
+    }
+    if dataset.is_empty() {
+        warn!("No finished {}x{} games found", width, height);
+    }
+    let output = arguments.value_of("output").unwrap();
+    dataset.write(output).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    info!("Dataset of {} samples written to {}", dataset.len(), output);
+    Ok(())
+}
+
+/// Runs the self-play tuner on the active config
+fn tune(arguments: &ArgMatches) -> io::Result<()> {
+    let mut params = TunerParams::default();
+    if let Ok(iterations) = value_t!(arguments, "iterations", usize) {
+        params.iterations = iterations;
+    }
+    if let Ok(games) = value_t!(arguments, "games", usize) {
+        params.games = games;
+    }
+    if let Ok(move_time) = value_t!(arguments, "move_time", u64) {
+        params.arena.time_budget = Duration::from_millis(move_time);
+    }
+    if let Ok(seed) = value_t!(arguments, "seed", u64) {
+        params.seed = seed;
+    }
+    let mut config = (*CONFIG.get()).clone();
+    let mut tuner = Tuner::new(config.get_default().clone(), params);
+    config.set_default(tuner.run());
+    let output = arguments.value_of("output").unwrap();
+    fs::write(output, format!("{}\n", config))?;
+    info!("Tuned config written to {}", output);
+    Ok(())
+}
+
+/// Trains evaluator weights of the active config on recorded games
+fn train_td(arguments: &ArgMatches) -> io::Result<()> {
+    let mut params = TdParams::default();
+    let invalid = |e: String| {
+        error!("{}", e);
+        io::Error::new(io::ErrorKind::InvalidInput, e)
+    };
+    if arguments.is_present("lambda") {
+        params.lambda = value_t!(arguments, "lambda", f32).map_err(|e| invalid(e.to_string()))?;
+    }
+    if arguments.is_present("alpha") {
+        params.alpha = value_t!(arguments, "alpha", f32).map_err(|e| invalid(e.to_string()))?;
+    }
+    if let Ok(epochs) = value_t!(arguments, "epochs", usize) {
+        params.epochs = epochs;
+    }
+    params.validate().map_err(invalid)?;
+    let replays = Replay::load_dir(arguments.value_of("replays").unwrap()).map_err(invalid)?;
+    let mut config = (*CONFIG.get()).clone();
+    let mut settings = config.get_default().clone();
+    let episodes: Vec<Episode> = replays
+        .iter()
+        .filter_map(|replay| Episode::from_replay(replay, &settings.evaluator))
+        .collect();
+    info!("Training on {} of {} recorded games", episodes.len(), replays.len());
+    let mut trainer = TdTrainer::new(settings.evaluator.clone(), params);
+    for (epoch, error) in trainer.train(&episodes).iter().enumerate() {
+        info!("Epoch {}: mean TD error {:.4}", epoch + 1, error);
+    }
+    settings.evaluator = trainer.get_config();
+    info!("Learned weights {}", settings.evaluator.build());
+    config.set_default(settings);
+    // a diverged run would write weights the server refuses to load
+    config
+        .validate()
+        .map_err(|e| invalid(format!("Training diverged: {}", e)))?;
+    let output = arguments.value_of("output").unwrap();
+    fs::write(output, format!("{}\n", config))?;
+    info!("Trained config written to {}", output);
+    Ok(())
+}